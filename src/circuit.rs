@@ -0,0 +1,722 @@
+// Circuit representation for rvecsim
+//
+// A Circuit is an ordered list of Gate operations on a fixed number of
+// qubits. It can be exported to and imported from OpenQASM 2.0 for use
+// with other tools, read from a simple JSON gate list (with the `serde`
+// feature), and applied to a QReg.
+
+use crate::{
+    rz_gate, QReg, CNOT_GATE, CPHASE_GATE, H_GATE, SDG_GATE, S_GATE, TDG_GATE, T_GATE, X_GATE,
+    Y_GATE, Z_GATE,
+};
+use ndarray::Array2;
+use num_complex::Complex64;
+use std::fmt::Write;
+
+/// A single gate operation on specific qubits.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Gate {
+    X { target: usize },
+    Y { target: usize },
+    Z { target: usize },
+    H { target: usize },
+    S { target: usize },
+    Sdg { target: usize },
+    T { target: usize },
+    Tdg { target: usize },
+    Rz { theta: f64, target: usize },
+    Cnot { control: usize, target: usize },
+    Cphase { control: usize, target: usize },
+}
+
+impl Gate {
+    /// Qubits this gate acts on, controls first.
+    pub fn qubits(&self) -> Vec<usize> {
+        match *self {
+            Gate::X { target }
+            | Gate::Y { target }
+            | Gate::Z { target }
+            | Gate::H { target }
+            | Gate::S { target }
+            | Gate::Sdg { target }
+            | Gate::T { target }
+            | Gate::Tdg { target }
+            | Gate::Rz { target, .. } => vec![target],
+            Gate::Cnot { control, target } | Gate::Cphase { control, target } => {
+                vec![control, target]
+            }
+        }
+    }
+
+    /// Gate matrix: 2x2 for single-qubit gates, 4x4 for two-qubit gates
+    /// (in the `apply2q` control/target basis order).
+    pub fn matrix(&self) -> Array2<Complex64> {
+        match *self {
+            Gate::X { .. } => X_GATE.clone(),
+            Gate::Y { .. } => Y_GATE.clone(),
+            Gate::Z { .. } => Z_GATE.clone(),
+            Gate::H { .. } => H_GATE.clone(),
+            Gate::S { .. } => S_GATE.clone(),
+            Gate::Sdg { .. } => SDG_GATE.clone(),
+            Gate::T { .. } => T_GATE.clone(),
+            Gate::Tdg { .. } => TDG_GATE.clone(),
+            Gate::Rz { theta, .. } => rz_gate(theta),
+            Gate::Cnot { .. } => CNOT_GATE.clone(),
+            Gate::Cphase { .. } => CPHASE_GATE.clone(),
+        }
+    }
+
+    /// The adjoint (inverse) of this gate.
+    pub fn inverse(&self) -> Gate {
+        match *self {
+            Gate::S { target } => Gate::Sdg { target },
+            Gate::Sdg { target } => Gate::S { target },
+            Gate::T { target } => Gate::Tdg { target },
+            Gate::Tdg { target } => Gate::T { target },
+            Gate::Rz { theta, target } => Gate::Rz { theta: -theta, target },
+            // X, Y, Z, H, CNOT and CPHASE are self-inverse
+            ref g => g.clone(),
+        }
+    }
+
+    /// OpenQASM 2.0 statement for this gate, e.g. "cx q[0],q[1];".
+    pub fn to_qasm(&self) -> String {
+        let name = match self {
+            Gate::X { .. } => "x".to_string(),
+            Gate::Y { .. } => "y".to_string(),
+            Gate::Z { .. } => "z".to_string(),
+            Gate::H { .. } => "h".to_string(),
+            Gate::S { .. } => "s".to_string(),
+            Gate::Sdg { .. } => "sdg".to_string(),
+            Gate::T { .. } => "t".to_string(),
+            Gate::Tdg { .. } => "tdg".to_string(),
+            Gate::Rz { theta, .. } => format!("rz({theta})"),
+            Gate::Cnot { .. } => "cx".to_string(),
+            Gate::Cphase { .. } => "cz".to_string(),
+        };
+        let args = self
+            .qubits()
+            .iter()
+            .map(|q| format!("q[{q}]"))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{name} {args};")
+    }
+}
+
+/// An ordered sequence of gates on `n` qubits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Circuit {
+    pub n: usize,
+    pub gates: Vec<Gate>,
+}
+
+impl Circuit {
+    /// Create an empty circuit on `n` qubits.
+    pub fn new(n: usize) -> Self {
+        Circuit { n, gates: Vec::new() }
+    }
+
+    /// Append a gate to the circuit.
+    pub fn push(&mut self, gate: Gate) -> &mut Self {
+        let qubits = gate.qubits();
+        for &q in &qubits {
+            assert!(q < self.n, "Invalid qubit {q}. Must be in [0, {})", self.n);
+        }
+        if let [control, target] = qubits[..] {
+            assert!(control != target, "Control and target must be different qubits");
+        }
+        self.gates.push(gate);
+        self
+    }
+
+    // ---- Gate recording (consume self for chaining) ----
+
+    /// Record a Pauli-X (NOT) gate on the target qubit.
+    pub fn x(mut self, target: usize) -> Self {
+        self.push(Gate::X { target });
+        self
+    }
+
+    /// Record a Pauli-Y gate on the target qubit.
+    pub fn y(mut self, target: usize) -> Self {
+        self.push(Gate::Y { target });
+        self
+    }
+
+    /// Record a Pauli-Z gate on the target qubit.
+    pub fn z(mut self, target: usize) -> Self {
+        self.push(Gate::Z { target });
+        self
+    }
+
+    /// Record a Hadamard gate on the target qubit.
+    pub fn h(mut self, target: usize) -> Self {
+        self.push(Gate::H { target });
+        self
+    }
+
+    /// Record an S (phase) gate on the target qubit.
+    pub fn s(mut self, target: usize) -> Self {
+        self.push(Gate::S { target });
+        self
+    }
+
+    /// Record an S-dagger gate on the target qubit.
+    pub fn sdg(mut self, target: usize) -> Self {
+        self.push(Gate::Sdg { target });
+        self
+    }
+
+    /// Record a T gate on the target qubit.
+    pub fn t(mut self, target: usize) -> Self {
+        self.push(Gate::T { target });
+        self
+    }
+
+    /// Record a T-dagger gate on the target qubit.
+    pub fn tdg(mut self, target: usize) -> Self {
+        self.push(Gate::Tdg { target });
+        self
+    }
+
+    /// Record a Z-rotation by `theta` radians on the target qubit.
+    pub fn rz(mut self, theta: f64, target: usize) -> Self {
+        self.push(Gate::Rz { theta, target });
+        self
+    }
+
+    /// Record a controlled-NOT gate.
+    pub fn cnot(mut self, control: usize, target: usize) -> Self {
+        self.push(Gate::Cnot { control, target });
+        self
+    }
+
+    /// Record a controlled-phase gate.
+    pub fn cphase(mut self, control: usize, target: usize) -> Self {
+        self.push(Gate::Cphase { control, target });
+        self
+    }
+
+    /// Number of gates in the circuit.
+    pub fn gate_count(&self) -> usize {
+        self.gates.len()
+    }
+
+    /// Circuit depth by greedy layering: each gate goes in the layer after
+    /// the latest one touching any of its qubits, so gates on disjoint
+    /// qubits share a layer. An empty circuit has depth 0.
+    pub fn depth(&self) -> usize {
+        let mut level = vec![0; self.n];
+        for gate in &self.gates {
+            let qubits = gate.qubits();
+            let layer = 1 + qubits.iter().map(|&q| level[q]).max().unwrap_or(0);
+            for q in qubits {
+                level[q] = layer;
+            }
+        }
+        level.into_iter().max().unwrap_or(0)
+    }
+
+    /// The inverse circuit: gates reversed and each replaced by its adjoint.
+    pub fn inverse(&self) -> Circuit {
+        Circuit {
+            n: self.n,
+            gates: self.gates.iter().rev().map(Gate::inverse).collect(),
+        }
+    }
+
+    /// Export the circuit as an OpenQASM 2.0 program.
+    ///
+    /// Qubit `i` of the circuit is `q[i]`, matching rvecsim's convention
+    /// that qubit 0 is the least significant bit.
+    pub fn to_qasm(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "OPENQASM 2.0;").unwrap();
+        writeln!(out, "include \"qelib1.inc\";").unwrap();
+        writeln!(out, "qreg q[{}];", self.n).unwrap();
+        for gate in &self.gates {
+            writeln!(out, "{}", gate.to_qasm()).unwrap();
+        }
+        out
+    }
+
+    /// Apply the circuit's gates in order to a quantum register.
+    pub fn apply(&self, mut q: QReg) -> QReg {
+        assert!(
+            q.n == self.n,
+            "Circuit has {} qubits but register has {}",
+            self.n,
+            q.n
+        );
+        q.run(&self.gates);
+        q
+    }
+}
+
+// ---- Gate Fusion ----
+
+impl Circuit {
+    /// Apply the circuit, fusing runs of single-qubit gates on the same
+    /// qubit into one 2x2 matrix so each run costs a single pass over the
+    /// state. Gates on other qubits commute with the run and don't break it;
+    /// a two-qubit gate touching the qubit flushes it first.
+    pub fn apply_fused(&self, mut q: QReg) -> QReg {
+        assert!(
+            q.n == self.n,
+            "Circuit has {} qubits but register has {}",
+            self.n,
+            q.n
+        );
+        let mut pending: Vec<Option<Array2<Complex64>>> = vec![None; self.n];
+        for gate in &self.gates {
+            match gate.qubits()[..] {
+                [target] => {
+                    let m = gate.matrix();
+                    pending[target] = Some(match pending[target].take() {
+                        Some(prev) => m.dot(&prev),
+                        None => m,
+                    });
+                }
+                [control, target] => {
+                    for qubit in [control, target] {
+                        if let Some(m) = pending[qubit].take() {
+                            q.apply1q(&m, qubit);
+                        }
+                    }
+                    q.apply2q(&gate.matrix(), control, target);
+                }
+                _ => unreachable!(),
+            }
+        }
+        for (qubit, m) in pending.into_iter().enumerate() {
+            if let Some(m) = m {
+                q.apply1q(&m, qubit);
+            }
+        }
+        q
+    }
+}
+
+// ---- OpenQASM Import ----
+
+/// Parse a subset of OpenQASM 2.0 into a Circuit.
+///
+/// Supports a single `qreg` declaration and the gates h, x, y, z, s, sdg,
+/// t, tdg, rz, cx and cz. Angles may be numbers or simple multiples of `pi`
+/// such as `-pi/4` or `3*pi/2`. The `OPENQASM 2.0` header and `include`
+/// statements are accepted and ignored, as are `//` comments. Errors name
+/// the offending line.
+pub fn from_qasm(src: &str) -> Result<Circuit, String> {
+    let mut circuit: Option<Circuit> = None;
+    let mut reg = "";
+    for (lineno, line) in src.lines().enumerate() {
+        let lineno = lineno + 1;
+        let line = line.split("//").next().unwrap_or("");
+        for stmt in line.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            let (word, rest) = match stmt.split_once(char::is_whitespace) {
+                Some((word, rest)) => (word, rest.trim()),
+                None => (stmt, ""),
+            };
+            // Split a parameterized gate like "rz(pi/2)" into name and angle
+            let (word, param) = match word.split_once('(') {
+                Some((name, p)) => match p.strip_suffix(')') {
+                    Some(p) => (name, Some(p)),
+                    None => return Err(format!("line {lineno}: malformed parameter in '{word}'")),
+                },
+                None => (word, None),
+            };
+            match word {
+                "OPENQASM" => {
+                    if rest != "2.0" {
+                        return Err(format!(
+                            "line {lineno}: unsupported OpenQASM version '{rest}'"
+                        ));
+                    }
+                }
+                "include" => {}
+                "qreg" => {
+                    if circuit.is_some() {
+                        return Err(format!(
+                            "line {lineno}: only one qreg declaration is supported"
+                        ));
+                    }
+                    let (name, size) =
+                        parse_qubit_ref(rest).map_err(|e| format!("line {lineno}: {e}"))?;
+                    reg = name;
+                    circuit = Some(Circuit::new(size));
+                }
+                _ => {
+                    let Some(c) = circuit.as_mut() else {
+                        return Err(format!(
+                            "line {lineno}: gate '{word}' used before qreg declaration"
+                        ));
+                    };
+                    let qubits = rest
+                        .split(',')
+                        .map(|arg| {
+                            let (name, idx) = parse_qubit_ref(arg.trim())?;
+                            if name != reg {
+                                Err(format!("unknown register '{name}'"))
+                            } else if idx >= c.n {
+                                Err(format!(
+                                    "qubit {name}[{idx}] out of range for qreg {reg}[{}]",
+                                    c.n
+                                ))
+                            } else {
+                                Ok(idx)
+                            }
+                        })
+                        .collect::<Result<Vec<_>, String>>()
+                        .map_err(|e| format!("line {lineno}: {e}"))?;
+                    let theta = match (word, param) {
+                        ("rz", Some(p)) => parse_angle(p)
+                            .ok_or_else(|| format!("line {lineno}: invalid angle '{p}'"))?,
+                        ("rz", None) => {
+                            return Err(format!("line {lineno}: gate 'rz' requires an angle"));
+                        }
+                        (_, Some(_)) => {
+                            return Err(format!("line {lineno}: gate '{word}' takes no parameters"));
+                        }
+                        (_, None) => 0.0,
+                    };
+                    let gate = match (word, qubits.as_slice()) {
+                        ("x", &[target]) => Gate::X { target },
+                        ("y", &[target]) => Gate::Y { target },
+                        ("z", &[target]) => Gate::Z { target },
+                        ("h", &[target]) => Gate::H { target },
+                        ("s", &[target]) => Gate::S { target },
+                        ("sdg", &[target]) => Gate::Sdg { target },
+                        ("t", &[target]) => Gate::T { target },
+                        ("tdg", &[target]) => Gate::Tdg { target },
+                        ("rz", &[target]) => Gate::Rz { theta, target },
+                        ("cx", &[control, target]) if control != target => {
+                            Gate::Cnot { control, target }
+                        }
+                        ("cz", &[control, target]) if control != target => {
+                            Gate::Cphase { control, target }
+                        }
+                        ("x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" | "rz" | "cx" | "cz", _) => {
+                            return Err(format!(
+                                "line {lineno}: invalid qubit arguments for gate '{word}'"
+                            ));
+                        }
+                        _ => return Err(format!("line {lineno}: unsupported gate '{word}'")),
+                    };
+                    c.push(gate);
+                }
+            }
+        }
+    }
+    circuit.ok_or_else(|| "missing qreg declaration".to_string())
+}
+
+/// One gate of a JSON circuit, e.g. `{"gate": "rz", "qubits": [0], "params": [1.57]}`.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct JsonGate {
+    gate: String,
+    qubits: Vec<usize>,
+    #[serde(default)]
+    params: Vec<f64>,
+}
+
+/// Parse a circuit from a JSON list of gates, e.g.
+/// `[{"gate": "h", "qubits": [0]}, {"gate": "cnot", "qubits": [0, 1]}]`.
+///
+/// Gate names are those of `to_qasm` (x, y, z, h, s, sdg, t, tdg, rz, cx,
+/// cz), plus cnot and cphase. `rz` takes its angle as `"params": [theta]`.
+/// The circuit spans qubits 0 through the highest index used.
+#[cfg(feature = "serde")]
+pub fn circuit_from_json(src: &str) -> Result<Circuit, String> {
+    let specs: Vec<JsonGate> = serde_json::from_str(src).map_err(|e| e.to_string())?;
+    let n = specs
+        .iter()
+        .flat_map(|g| g.qubits.iter())
+        .max()
+        .map_or(0, |&q| q + 1);
+    let mut circuit = Circuit::new(n);
+    for (i, spec) in specs.iter().enumerate() {
+        let name = spec.gate.as_str();
+        let theta = match (name, spec.params.as_slice()) {
+            ("rz", &[theta]) => theta,
+            ("rz", _) => return Err(format!("gate {i}: 'rz' requires one parameter")),
+            (_, &[]) => 0.0,
+            (_, _) => return Err(format!("gate {i}: '{name}' takes no parameters")),
+        };
+        let gate = match (name, spec.qubits.as_slice()) {
+            ("x", &[target]) => Gate::X { target },
+            ("y", &[target]) => Gate::Y { target },
+            ("z", &[target]) => Gate::Z { target },
+            ("h", &[target]) => Gate::H { target },
+            ("s", &[target]) => Gate::S { target },
+            ("sdg", &[target]) => Gate::Sdg { target },
+            ("t", &[target]) => Gate::T { target },
+            ("tdg", &[target]) => Gate::Tdg { target },
+            ("rz", &[target]) => Gate::Rz { theta, target },
+            ("cx" | "cnot", &[control, target]) if control != target => {
+                Gate::Cnot { control, target }
+            }
+            ("cz" | "cphase", &[control, target]) if control != target => {
+                Gate::Cphase { control, target }
+            }
+            (
+                "x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" | "rz" | "cx" | "cnot" | "cz"
+                | "cphase",
+                _,
+            ) => return Err(format!("gate {i}: invalid qubit arguments for gate '{name}'")),
+            _ => return Err(format!("gate {i}: unsupported gate '{name}'")),
+        };
+        circuit.push(gate);
+    }
+    Ok(circuit)
+}
+
+/// Parse an angle: a number, or a product/quotient involving `pi`.
+fn parse_angle(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, s),
+    };
+    let factor = |tok: &str| -> Option<f64> {
+        match tok.trim() {
+            "pi" => Some(std::f64::consts::PI),
+            t => t.parse::<f64>().ok(),
+        }
+    };
+    // Evaluate left to right over '*' and '/'
+    let mut value = None;
+    let mut op = '*';
+    let mut start = 0;
+    for (i, ch) in s.char_indices().chain(std::iter::once((s.len(), '*'))) {
+        if ch == '*' || ch == '/' {
+            let x = factor(&s[start..i])?;
+            value = Some(match (value, op) {
+                (None, _) => x,
+                (Some(v), '*') => v * x,
+                (Some(v), _) => v / x,
+            });
+            op = ch;
+            start = i + 1;
+        }
+    }
+    value.map(|v| sign * v)
+}
+
+/// Parse a register reference like "q[3]" into ("q", 3).
+fn parse_qubit_ref(s: &str) -> Result<(&str, usize), String> {
+    let (name, rest) = s
+        .split_once('[')
+        .ok_or_else(|| format!("expected name[index], got '{s}'"))?;
+    let idx = rest
+        .strip_suffix(']')
+        .and_then(|idx| idx.trim().parse::<usize>().ok())
+        .ok_or_else(|| format!("invalid index in '{s}'"))?;
+    Ok((name.trim(), idx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ket;
+
+    #[test]
+    fn test_bell_to_qasm() {
+        let mut c = Circuit::new(2);
+        c.push(Gate::H { target: 0 });
+        c.push(Gate::Cnot { control: 0, target: 1 });
+        assert_eq!(
+            c.to_qasm(),
+            "OPENQASM 2.0;\n\
+             include \"qelib1.inc\";\n\
+             qreg q[2];\n\
+             h q[0];\n\
+             cx q[0],q[1];\n"
+        );
+    }
+
+    #[test]
+    fn test_gate_count_and_depth() {
+        let ghz = Circuit::new(3).h(0).cnot(0, 1).cnot(1, 2);
+        assert_eq!(ghz.gate_count(), 3);
+        assert_eq!(ghz.depth(), 3);
+        // Gates on disjoint qubits share a layer
+        let c = Circuit::new(4).h(0).h(1).h(2).cnot(0, 1).cnot(2, 3).t(0);
+        assert_eq!(c.gate_count(), 6);
+        assert_eq!(c.depth(), 3);
+        assert_eq!(Circuit::new(2).depth(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_bell_from_json() {
+        let json = r#"[{"gate": "h", "qubits": [0]}, {"gate": "cnot", "qubits": [0, 1]}]"#;
+        let c = circuit_from_json(json).unwrap();
+        assert_eq!(c, Circuit::new(2).h(0).cnot(0, 1));
+        assert!(c.apply(ket("00")).isclose(&ket("00").h(0).cnot(0, 1)));
+
+        let c = circuit_from_json(r#"[{"gate": "rz", "qubits": [2], "params": [0.5]}]"#).unwrap();
+        assert_eq!(c.n, 3);
+        assert_eq!(c.gates, vec![Gate::Rz { theta: 0.5, target: 2 }]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_errors() {
+        let err = |src: &str| circuit_from_json(src).unwrap_err();
+        assert_eq!(err(r#"[{"gate": "foo", "qubits": [0]}]"#), "gate 0: unsupported gate 'foo'");
+        assert_eq!(
+            err(r#"[{"gate": "h", "qubits": [0]}, {"gate": "cx", "qubits": [1, 1]}]"#),
+            "gate 1: invalid qubit arguments for gate 'cx'"
+        );
+        assert_eq!(err(r#"[{"gate": "rz", "qubits": [0]}]"#), "gate 0: 'rz' requires one parameter");
+        assert!(err(r#"{"gate": "h"}"#).contains("expected a sequence"));
+    }
+
+    #[test]
+    fn test_recorded_bell_circuit() {
+        let bell = Circuit::new(2).h(0).cnot(0, 1);
+        assert_eq!(bell.gates.len(), 2);
+        assert!(bell.apply(ket("00")).isclose(&ket("00").h(0).cnot(0, 1)));
+        // The same circuit can be replayed on other inputs
+        assert!(bell.apply(ket("01")).isclose(&ket("01").h(0).cnot(0, 1)));
+        assert!(bell.apply(ket("11")).isclose(&ket("11").h(0).cnot(0, 1)));
+    }
+
+    #[test]
+    fn test_inverse_random_circuit() {
+        use crate::random;
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut c = Circuit::new(3);
+        for _ in 0..40 {
+            let target = rng.gen_range(0..3);
+            let control = (target + rng.gen_range(1..3)) % 3;
+            c.push(match rng.gen_range(0..11) {
+                0 => Gate::X { target },
+                1 => Gate::Y { target },
+                2 => Gate::Z { target },
+                3 => Gate::H { target },
+                4 => Gate::S { target },
+                5 => Gate::Sdg { target },
+                6 => Gate::T { target },
+                7 => Gate::Tdg { target },
+                8 => Gate::Rz { theta: rng.gen_range(-3.0..3.0), target },
+                9 => Gate::Cnot { control, target },
+                _ => Gate::Cphase { control, target },
+            });
+        }
+        let input = random(3, &mut rng);
+        let output = c.inverse().apply(c.apply(input.clone()));
+        assert!(output.isclose(&input));
+    }
+
+    #[test]
+    fn test_inverse_adjoints() {
+        let c = Circuit::new(2).h(0).s(0).t(1).rz(0.5, 1).cnot(0, 1);
+        let inv = Circuit::new(2).cnot(0, 1).rz(-0.5, 1).tdg(1).sdg(0).h(0);
+        assert_eq!(c.inverse(), inv);
+    }
+
+    #[test]
+    fn test_parse_angle() {
+        let pi = std::f64::consts::PI;
+        assert_eq!(parse_angle("0.25"), Some(0.25));
+        assert_eq!(parse_angle("pi"), Some(pi));
+        assert_eq!(parse_angle("-pi/4"), Some(-pi / 4.0));
+        assert_eq!(parse_angle("3*pi/2"), Some(3.0 * pi / 2.0));
+        assert_eq!(parse_angle("tau"), None);
+    }
+
+    #[test]
+    fn test_apply_fused_matches_apply() {
+        use crate::random;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let c = Circuit::new(3)
+            .h(0)
+            .x(0)
+            .h(0)
+            .t(1)
+            .s(0)
+            .rz(0.7, 1)
+            .cnot(0, 1)
+            .y(1)
+            .h(2)
+            .sdg(2)
+            .cphase(2, 0)
+            .tdg(0)
+            .z(1);
+        let input = random(3, &mut StdRng::seed_from_u64(17));
+        assert!(c.apply_fused(input.clone()).isclose(&c.apply(input)));
+
+        // H X H = Z
+        let hxh = Circuit::new(1).h(0).x(0).h(0);
+        assert!(hxh.apply_fused(ket("+")).isclose(&ket("-")));
+    }
+
+    #[test]
+    fn test_qasm_round_trip() {
+        let c = Circuit::new(3)
+            .h(0)
+            .cnot(0, 1)
+            .s(1)
+            .cphase(1, 2)
+            .y(2)
+            .t(0)
+            .tdg(2)
+            .sdg(1)
+            .rz(0.3, 2);
+        let parsed = from_qasm(&c.to_qasm()).unwrap();
+        assert_eq!(parsed, c);
+
+        let direct = ket("000")
+            .h(0)
+            .cnot(0, 1)
+            .s(1)
+            .cphase(1, 2)
+            .y(2)
+            .t(0)
+            .tdg(2)
+            .sdg(1)
+            .rz(0.3, 2);
+        assert!(parsed.apply(ket("000")).isclose(&direct));
+    }
+
+    #[test]
+    fn test_from_qasm_comments_and_whitespace() {
+        let src = "OPENQASM 2.0;\n\
+                   // Bell state\n\
+                   qreg r[2];\n\
+                   h r[0]; cx r[0], r[1];  // entangle\n";
+        let c = from_qasm(src).unwrap();
+        assert_eq!(c.gates, vec![Gate::H { target: 0 }, Gate::Cnot { control: 0, target: 1 }]);
+    }
+
+    #[test]
+    fn test_from_qasm_errors() {
+        let err = from_qasm("h q[0];").unwrap_err();
+        assert!(err.contains("before qreg"), "{err}");
+        let err = from_qasm("qreg q[2];\nrx q[0];").unwrap_err();
+        assert!(err.contains("line 2: unsupported gate 'rx'"), "{err}");
+        let err = from_qasm("qreg q[2];\nh q[2];").unwrap_err();
+        assert!(err.contains("out of range"), "{err}");
+        let err = from_qasm("qreg q[2];\ncx q[0];").unwrap_err();
+        assert!(err.contains("invalid qubit arguments"), "{err}");
+        let err = from_qasm("qreg q[2];\nh p[0];").unwrap_err();
+        assert!(err.contains("unknown register 'p'"), "{err}");
+        let err = from_qasm("qreg q[two];").unwrap_err();
+        assert!(err.contains("invalid index"), "{err}");
+        assert!(from_qasm("OPENQASM 2.0;").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid qubit 2")]
+    fn test_push_invalid_qubit() {
+        Circuit::new(2).push(Gate::X { target: 2 });
+    }
+}
@@ -0,0 +1,90 @@
+// Classical register for rvecsim
+//
+// A ClassicalReg holds measurement outcomes recorded during a computation,
+// so mid-circuit measurements can be stored alongside the quantum state and
+// used later to control gates. Each bit is None until it is written.
+
+use std::fmt;
+use std::ops::Index;
+
+/// A register of classical bits, each unset (`None`) or holding 0 or 1.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClassicalReg {
+    pub bits: Vec<Option<usize>>,
+}
+
+impl ClassicalReg {
+    /// Create a register of `n` unset classical bits.
+    pub fn new(n: usize) -> Self {
+        ClassicalReg { bits: vec![None; n] }
+    }
+
+    /// Number of classical bits.
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// True if the register has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// Value of classical bit `cbit`, or `None` if it has not been written.
+    pub fn get(&self, cbit: usize) -> Option<usize> {
+        self.bits[cbit]
+    }
+
+    /// Write `value` (0 or 1) to classical bit `cbit`.
+    pub fn set(&mut self, cbit: usize, value: usize) {
+        assert!(
+            cbit < self.bits.len(),
+            "Invalid classical bit {cbit}. Must be in [0, {})",
+            self.bits.len()
+        );
+        assert!(value <= 1, "Classical bit value must be 0 or 1, got {value}");
+        self.bits[cbit] = Some(value);
+    }
+}
+
+impl Index<usize> for ClassicalReg {
+    type Output = Option<usize>;
+    fn index(&self, cbit: usize) -> &Option<usize> {
+        &self.bits[cbit]
+    }
+}
+
+/// Bits are shown most significant first, like `QReg` terms, with `_` for
+/// unset bits, e.g. "1_0".
+impl fmt::Display for ClassicalReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bit in self.bits.iter().rev() {
+            match bit {
+                Some(b) => write!(f, "{b}")?,
+                None => write!(f, "_")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_display() {
+        let mut c = ClassicalReg::new(3);
+        assert_eq!(c.to_string(), "___");
+        c.set(0, 0);
+        c.set(2, 1);
+        assert_eq!(c.get(0), Some(0));
+        assert_eq!(c[1], None);
+        assert_eq!(c.to_string(), "1_0");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid classical bit 2")]
+    fn test_set_out_of_range() {
+        ClassicalReg::new(2).set(2, 1);
+    }
+}
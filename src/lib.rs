@@ -5,8 +5,11 @@ use ndarray::{array, Array1, Array2};
 use num_complex::Complex64;
 use rand::Rng;
 use rayon::prelude::*;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::fmt;
-use std::ops::{Add, Mul, Sub};
+use std::ops::{Add, Index, IndexMut, Mul, Sub};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::LazyLock;
 
 // ---- Thread-safe pointer wrapper for parallel mutation ----
@@ -29,6 +32,74 @@ impl<T: Copy> SendPtr<T> {
     }
 }
 
+// ---- Parallelism Threshold ----
+
+/// State vectors shorter than this are processed serially; rayon's
+/// scheduling overhead outweighs the gain for small registers.
+pub const DEFAULT_PAR_THRESHOLD: usize = 1 << 14;
+
+static PAR_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_PAR_THRESHOLD);
+
+thread_local! {
+    static PAR_THRESHOLD_OVERRIDE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Current minimum state vector length for parallel gate application: the
+/// override set by an enclosing `with_par_threshold` on this thread, else
+/// the process-wide default.
+pub fn par_threshold() -> usize {
+    PAR_THRESHOLD_OVERRIDE
+        .with(Cell::get)
+        .unwrap_or_else(|| PAR_THRESHOLD.load(Ordering::Relaxed))
+}
+
+/// Set the process-wide minimum state vector length for parallel gate
+/// application. Use 0 to always parallelize and `usize::MAX` to never
+/// parallelize. Prefer `with_par_threshold` for temporary changes.
+pub fn set_par_threshold(len: usize) {
+    PAR_THRESHOLD.store(len, Ordering::Relaxed);
+}
+
+/// Run `f` with the parallelism threshold set to `len` on the calling
+/// thread only, restoring the previous value afterwards (even on panic).
+/// Other threads, e.g. concurrently running tests, are unaffected.
+pub fn with_par_threshold<R>(len: usize, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<usize>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            PAR_THRESHOLD_OVERRIDE.with(|o| o.set(self.0));
+        }
+    }
+    let _restore = Restore(PAR_THRESHOLD_OVERRIDE.with(|o| o.replace(Some(len))));
+    f()
+}
+
+/// Run `f` for every index in `0..len`, in parallel once `len` reaches the
+/// parallelism threshold and serially otherwise.
+fn for_each_index<F: Fn(usize) + Send + Sync>(len: usize, f: F) {
+    if len >= par_threshold() {
+        (0..len).into_par_iter().for_each(f);
+    } else {
+        (0..len).for_each(f);
+    }
+}
+
+/// Sum `f` over every index in `0..len`, in parallel once `len` reaches the
+/// parallelism threshold. Partial sums are taken over fixed-size chunks and
+/// combined in order, so the result does not depend on thread scheduling.
+fn sum_over_indices<F: Fn(usize) -> f64 + Send + Sync>(len: usize, f: F) -> f64 {
+    const CHUNK: usize = 1 << 12;
+    if len >= par_threshold() {
+        let partials: Vec<f64> = (0..len.div_ceil(CHUNK))
+            .into_par_iter()
+            .map(|c| (c * CHUNK..len.min((c + 1) * CHUNK)).map(&f).sum())
+            .collect();
+        partials.iter().sum()
+    } else {
+        (0..len).map(f).sum()
+    }
+}
+
 // ---- Complex Constants ----
 
 const ZERO: Complex64 = Complex64::new(0.0, 0.0);
@@ -38,20 +109,48 @@ const IM: Complex64 = Complex64::new(0.0, 1.0);
 const NEG_IM: Complex64 = Complex64::new(0.0, -1.0);
 const S2: Complex64 = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
 const NEG_S2: Complex64 = Complex64::new(-std::f64::consts::FRAC_1_SQRT_2, 0.0);
+const S2_IM: Complex64 = Complex64::new(0.0, std::f64::consts::FRAC_1_SQRT_2);
+const NEG_S2_IM: Complex64 = Complex64::new(0.0, -std::f64::consts::FRAC_1_SQRT_2);
 
 // ---- Utility Functions ----
 
 /// Return the number of qubits for a state vector of length `vl`.
+///
+/// Computes `floor(log2(vl))`, so lengths that are not a power of 2 are
+/// silently rounded down (e.g. `nqubits(6) == 2`). Use [`nqubits_checked`]
+/// when the length has not already been validated.
 pub fn nqubits(vl: usize) -> usize {
     assert!(vl > 0, "Vector length must be positive, got {vl}");
     (vl as f64).log2().floor() as usize
 }
 
+/// Return the number of qubits for a state vector of length `vl`,
+/// or `None` if `vl` is not a power of 2.
+pub fn nqubits_checked(vl: usize) -> Option<usize> {
+    if vl.is_power_of_two() {
+        Some(vl.trailing_zeros() as usize)
+    } else {
+        None
+    }
+}
+
 /// Flip bit `b` in index `i` using XOR.
 pub fn conjugate_index(i: usize, b: usize) -> usize {
     i ^ (1 << b)
 }
 
+/// Gather the bits of `i` at positions `qubits` into a compact index,
+/// with bit `j` of the result taken from bit `qubits[j]` of `i`.
+fn gather_bits(i: usize, qubits: &[usize]) -> usize {
+    qubits
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (j, &q)| acc | (((i >> q) & 1) << j))
+}
+
+/// Amplitude groups smaller than this are skipped by `apply1q`/`apply2q`.
+pub const SKIP_TOL: f64 = 1e-8;
+
 // ---- Gate Matrices ----
 
 pub static I_GATE: LazyLock<Array2<Complex64>> = LazyLock::new(|| {
@@ -78,6 +177,45 @@ pub static S_GATE: LazyLock<Array2<Complex64>> = LazyLock::new(|| {
     array![[ONE, ZERO], [ZERO, IM]]
 });
 
+pub static SDG_GATE: LazyLock<Array2<Complex64>> = LazyLock::new(|| {
+    array![[ONE, ZERO], [ZERO, NEG_IM]]
+});
+
+pub static T_GATE: LazyLock<Array2<Complex64>> = LazyLock::new(|| {
+    array![[ONE, ZERO], [ZERO, Complex64::from_polar(1.0, std::f64::consts::FRAC_PI_4)]]
+});
+
+pub static TDG_GATE: LazyLock<Array2<Complex64>> = LazyLock::new(|| {
+    array![[ONE, ZERO], [ZERO, Complex64::from_polar(1.0, -std::f64::consts::FRAC_PI_4)]]
+});
+
+/// Z-rotation matrix RZ(theta) = diag(e^{-i theta/2}, e^{i theta/2}).
+pub fn rz_gate(theta: f64) -> Array2<Complex64> {
+    array![
+        [Complex64::from_polar(1.0, -theta / 2.0), ZERO],
+        [ZERO, Complex64::from_polar(1.0, theta / 2.0)]
+    ]
+}
+
+/// X-rotation matrix RX(theta) = exp(-i theta X / 2).
+pub fn rx_gate(theta: f64) -> Array2<Complex64> {
+    let c = Complex64::new((theta / 2.0).cos(), 0.0);
+    let s = Complex64::new(0.0, -(theta / 2.0).sin());
+    array![[c, s], [s, c]]
+}
+
+/// Y-rotation matrix RY(theta) = exp(-i theta Y / 2).
+pub fn ry_gate(theta: f64) -> Array2<Complex64> {
+    let c = Complex64::new((theta / 2.0).cos(), 0.0);
+    let s = Complex64::new((theta / 2.0).sin(), 0.0);
+    array![[c, -s], [s, c]]
+}
+
+/// Phase matrix P(phi) = diag(1, e^{i phi}).
+pub fn p_gate(phi: f64) -> Array2<Complex64> {
+    array![[ONE, ZERO], [ZERO, Complex64::from_polar(1.0, phi)]]
+}
+
 pub static CNOT_GATE: LazyLock<Array2<Complex64>> = LazyLock::new(|| {
     array![
         [ONE,  ZERO, ZERO, ZERO],
@@ -96,6 +234,44 @@ pub static CPHASE_GATE: LazyLock<Array2<Complex64>> = LazyLock::new(|| {
     ]
 });
 
+/// Controlled-phase matrix CP(phi) = diag(1, 1, 1, e^{i phi}).
+pub fn cp_gate(phi: f64) -> Array2<Complex64> {
+    let p = Complex64::from_polar(1.0, phi);
+    array![
+        [ONE,  ZERO, ZERO, ZERO],
+        [ZERO, ONE,  ZERO, ZERO],
+        [ZERO, ZERO, ONE,  ZERO],
+        [ZERO, ZERO, ZERO, p   ]
+    ]
+}
+
+/// Givens rotation G(theta), a real rotation by theta in the {|01>, |10>}
+/// subspace that leaves |00> and |11> alone.
+pub fn givens_gate(theta: f64) -> Array2<Complex64> {
+    let c = Complex64::new(theta.cos(), 0.0);
+    let s = Complex64::new(theta.sin(), 0.0);
+    array![
+        [ONE,  ZERO, ZERO, ZERO],
+        [ZERO, c,    -s,   ZERO],
+        [ZERO, s,    c,    ZERO],
+        [ZERO, ZERO, ZERO, ONE ]
+    ]
+}
+
+/// Check that `m` is square and unitary, i.e. M†M is the identity to
+/// within `tol` in every entry.
+pub fn is_unitary(m: &Array2<Complex64>, tol: f64) -> bool {
+    let (rows, cols) = m.dim();
+    if rows != cols {
+        return false;
+    }
+    let mdag_m = m.t().mapv(|x| x.conj()).dot(m);
+    mdag_m.indexed_iter().all(|((i, j), &x)| {
+        let expected = if i == j { ONE } else { ZERO };
+        (x - expected).norm() <= tol
+    })
+}
+
 // ---- Kronecker Product ----
 
 fn kron(a: &Array1<Complex64>, b: &Array1<Complex64>) -> Array1<Complex64> {
@@ -109,6 +285,66 @@ fn kron(a: &Array1<Complex64>, b: &Array1<Complex64>) -> Array1<Complex64> {
     result
 }
 
+// ---- Eigenvalues ----
+
+/// Eigenvalues of a Hermitian matrix, in ascending order.
+///
+/// The n x n Hermitian matrix H = A + iB is embedded in the 2n x 2n real
+/// symmetric matrix [[A, -B], [B, A]], which has each eigenvalue of H twice,
+/// and diagonalized with the cyclic Jacobi method.
+fn hermitian_eigenvalues(m: &Array2<Complex64>) -> Vec<f64> {
+    let n = m.nrows();
+    let size = 2 * n;
+    let mut a = Array2::<f64>::zeros((size, size));
+    for i in 0..n {
+        for j in 0..n {
+            let z = m[[i, j]];
+            a[[i, j]] = z.re;
+            a[[i + n, j + n]] = z.re;
+            a[[i, j + n]] = -z.im;
+            a[[i + n, j]] = z.im;
+        }
+    }
+
+    for _ in 0..100 {
+        let off: f64 = (0..size)
+            .flat_map(|p| (p + 1..size).map(move |q| (p, q)))
+            .map(|(p, q)| a[[p, q]] * a[[p, q]])
+            .sum();
+        if off < 1e-24 {
+            break;
+        }
+        for p in 0..size {
+            for q in p + 1..size {
+                let apq = a[[p, q]];
+                if apq.abs() < 1e-300 {
+                    continue;
+                }
+                // Rotation angle chosen to zero a[p][q]
+                let theta = (a[[q, q]] - a[[p, p]]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..size {
+                    let (akp, akq) = (a[[k, p]], a[[k, q]]);
+                    a[[k, p]] = c * akp - s * akq;
+                    a[[k, q]] = s * akp + c * akq;
+                }
+                for k in 0..size {
+                    let (apk, aqk) = (a[[p, k]], a[[q, k]]);
+                    a[[p, k]] = c * apk - s * aqk;
+                    a[[q, k]] = s * apk + c * aqk;
+                }
+            }
+        }
+    }
+
+    let mut eig: Vec<f64> = a.diag().to_vec();
+    eig.sort_by(|x, y| x.total_cmp(y));
+    // Each eigenvalue appears twice in the real embedding
+    eig.into_iter().step_by(2).collect()
+}
+
 // ---- Formatting ----
 
 /// Round to `n` significant figures to eliminate floating-point ULP noise.
@@ -133,22 +369,102 @@ fn format_real(x: f64) -> String {
 }
 
 /// Format a complex coefficient for display.
-/// Returns just the real part if purely real, otherwise the full complex number.
+/// Returns just the real part if purely real, just the imaginary part if
+/// purely imaginary, otherwise the full complex number with the sign of the
+/// imaginary part folded into the operator (e.g. "1.0-1.0i").
 fn qcoef(a: Complex64) -> String {
     let re = round_sigfigs(a.re, 15);
     let im = round_sigfigs(a.im, 15);
     if im.abs() < 1e-8 {
         format_real(re)
+    } else if re.abs() < 1e-8 {
+        format!("{}i", format_real(im))
+    } else if im < 0.0 {
+        format!("{}-{}i", format_real(re), format_real(-im))
     } else {
         format!("{}+{}i", format_real(re), format_real(im))
     }
 }
 
+/// Format a positive magnitude for LaTeX, recognizing 1/sqrt(k) and 1/m.
+/// Returns an empty string for 1.
+fn latex_magnitude(x: f64) -> String {
+    if (x - 1.0).abs() < 1e-10 {
+        return String::new();
+    }
+    let k = 1.0 / (x * x);
+    if (k - k.round()).abs() < 1e-8 && k.round() <= 1e6 {
+        let k = k.round() as u64;
+        let m = (k as f64).sqrt().round() as u64;
+        if m * m == k {
+            return format!("\\frac{{1}}{{{m}}}");
+        }
+        return format!("\\frac{{1}}{{\\sqrt{{{k}}}}}");
+    }
+    format_real(x)
+}
+
+/// Format a term coefficient for LaTeX as (sign, body), where `c` is the
+/// coefficient relative to any common factor pulled out front.
+fn latex_coef(c: Complex64) -> (bool, String) {
+    let re = round_sigfigs(c.re, 15);
+    let im = round_sigfigs(c.im, 15);
+    if im.abs() < 1e-8 {
+        (re < 0.0, latex_magnitude(re.abs()))
+    } else if re.abs() < 1e-8 {
+        (im < 0.0, format!("{}i", latex_magnitude(im.abs())))
+    } else {
+        (false, format!("({})", qcoef(c)))
+    }
+}
+
+/// Format a phase angle in (-pi, pi] as a multiple of π when it is a
+/// fraction with a small denominator ("π/2", "-3π/4"), else in radians.
+fn format_phase(phi: f64) -> String {
+    let t = phi / std::f64::consts::PI;
+    for den in 1..=12u32 {
+        let num = t * den as f64;
+        if (num - num.round()).abs() < 1e-9 {
+            let num = num.round() as i64;
+            let sign = if num < 0 { "-" } else { "" };
+            let num = num.unsigned_abs();
+            let top = if num == 1 { "π".to_string() } else { format!("{num}π") };
+            return if den == 1 { format!("{sign}{top}") } else { format!("{sign}{top}/{den}") };
+        }
+    }
+    format_real(round_sigfigs(phi, 15))
+}
+
+/// Format a complex coefficient in polar form "r·e^{iφ}", or just "r" when
+/// the phase is zero.
+fn qcoef_polar(a: Complex64) -> String {
+    let (r, phi) = a.to_polar();
+    if phi.abs() < 1e-9 {
+        return format_real(r);
+    }
+    let phase = format_phase(phi);
+    match phase.strip_prefix('-') {
+        Some(abs) => format!("{}·e^{{-i{abs}}}", format_real(r)),
+        None => format!("{}·e^{{i{phase}}}", format_real(r)),
+    }
+}
+
 /// Format a single term of a quantum state as "coef|binary>".
 fn qterm(i: usize, qi: Complex64, n: usize) -> String {
     format!("{}|{:0>width$b}>", qcoef(qi), i, width = n)
 }
 
+/// Read a classical bit that must already have been measured.
+fn classical_bit(creg: &ClassicalReg, cbit: usize) -> usize {
+    assert!(
+        cbit < creg.len(),
+        "Invalid classical bit {cbit}. Must be in [0, {})",
+        creg.len()
+    );
+    creg.get(cbit)
+        .unwrap_or_else(|| panic!("Classical bit {cbit} has not been measured"))
+}
+
 // ---- Quantum Register ----
 
 #[derive(Clone)]
@@ -160,34 +476,67 @@ pub struct QReg {
 impl QReg {
     /// Create a new quantum register from a vector of complex amplitudes.
     /// The vector length must be a power of 2. The state is normalized.
+    /// Panics on invalid input; see `try_new` for the checked version.
     pub fn new(register: Vec<Complex64>) -> Self {
-        assert!(!register.is_empty(), "Register cannot be empty");
-        let n = register.len();
-        assert!(
-            n.is_power_of_two(),
-            "Register length must be power of 2, got {n}"
-        );
-        let v = Array1::from_vec(register);
-        let mut qreg = QReg {
-            v,
-            n: nqubits(n),
-        };
+        Self::try_new(register).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error for an empty, non-power-of-2 or
+    /// zero-norm vector instead of panicking.
+    pub fn try_new(register: Vec<Complex64>) -> Result<Self, String> {
+        if register.is_empty() {
+            return Err("Register cannot be empty".to_string());
+        }
+        let len = register.len();
+        let n = nqubits_checked(len)
+            .ok_or_else(|| format!("Register length must be power of 2, got {len}"))?;
+        let mut qreg = QReg { v: Array1::from_vec(register), n };
+        if qreg.norm() <= 1e-10 {
+            return Err("Cannot normalize zero vector".to_string());
+        }
         qreg.normalize();
-        qreg
+        Ok(qreg)
     }
 
     /// Create a quantum register from an existing Array1.
     fn from_array(v: Array1<Complex64>) -> Self {
-        let n = v.len();
-        assert!(n > 0 && n.is_power_of_two());
-        let mut qreg = QReg {
-            v,
-            n: nqubits(n),
-        };
+        let len = v.len();
+        let n = nqubits_checked(len)
+            .unwrap_or_else(|| panic!("Register length must be power of 2, got {len}"));
+        let mut qreg = QReg { v, n };
         qreg.normalize();
         qreg
     }
 
+    /// Create a quantum register from an existing Array1 without
+    /// normalizing, for intermediate states such as projector outputs.
+    ///
+    /// The length must still be a power of 2, but the caller is responsible
+    /// for normalizing (e.g. with `normalize()`) before measuring or
+    /// comparing: methods that compute probabilities assume unit norm.
+    pub fn from_array_raw(v: Array1<Complex64>) -> Self {
+        let len = v.len();
+        let n = nqubits_checked(len)
+            .unwrap_or_else(|| panic!("Register length must be power of 2, got {len}"));
+        QReg { v, n }
+    }
+
+    /// Save the amplitudes to `path` as a 1-D complex128 .npy array, readable
+    /// with `numpy.load`.
+    #[cfg(feature = "npy")]
+    pub fn save_npy(&self, path: &str) -> std::io::Result<()> {
+        ndarray_npy::write_npy(path, &self.v).map_err(std::io::Error::other)
+    }
+
+    /// Load a state saved by `save_npy` (or any 1-D complex128 .npy array of
+    /// power-of-2 length). The amplitudes are normalized.
+    #[cfg(feature = "npy")]
+    pub fn load_npy(path: &str) -> std::io::Result<QReg> {
+        let v: Array1<Complex64> = ndarray_npy::read_npy(path).map_err(std::io::Error::other)?;
+        QReg::try_new(v.to_vec())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
     /// Calculate the L2 norm of the state vector.
     pub fn norm(&self) -> f64 {
         self.v.iter().map(|x| x.norm_sqr()).sum::<f64>().sqrt()
@@ -200,24 +549,195 @@ impl QReg {
         self.v.mapv_inplace(|x| x / norm);
     }
 
+    /// Global phase in radians, in (-pi, pi]: the argument of the first
+    /// significant amplitude. This is the phase `canonicalize_phase` removes.
+    pub fn global_phase(&self) -> f64 {
+        self.v
+            .iter()
+            .find(|qi| qi.norm() > 1e-8)
+            .map_or(0.0, |first| first.arg())
+    }
+
+    /// Remove the global phase so the first significant amplitude is real
+    /// and positive. States equal up to a global phase then compare equal.
+    pub fn canonicalize_phase(&mut self) -> &mut Self {
+        let phase = Complex64::from_polar(1.0, -self.global_phase());
+        self.v.mapv_inplace(|x| x * phase);
+        self
+    }
+
     /// Return string representation of significant terms in the quantum state.
     pub fn terms(&self) -> String {
+        self.terms_with_tol(1e-8)
+    }
+
+    /// Like `terms()`, but only terms with magnitude above `tol` are shown.
+    pub fn terms_with_tol(&self, tol: f64) -> String {
         self.v
             .iter()
             .enumerate()
-            .filter(|(_, qi)| qi.norm() > 1e-8)
+            .filter(|(_, qi)| qi.norm() > tol)
             .map(|(i, &qi)| qterm(i, qi, self.n))
             .collect::<Vec<_>>()
             .join(" ")
     }
 
+    /// Like `terms()`, but each coefficient is shown in polar form
+    /// `r·e^{iφ}`, with φ in units of π where it is a simple fraction.
+    /// Zero phases are omitted, so real positive amplitudes print as `r`.
+    pub fn terms_polar(&self) -> String {
+        self.v
+            .iter()
+            .enumerate()
+            .filter(|(_, qi)| qi.norm() > 1e-8)
+            .map(|(i, &qi)| format!("{}|{:0>width$b}>", qcoef_polar(qi), i, width = self.n))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Like `terms()`, but bitstrings are formatted to `width` characters
+    /// instead of n. A wider width pads with leading zeros; a narrower one
+    /// keeps only the rightmost `width` characters (the low qubits).
+    pub fn terms_width(&self, width: usize) -> String {
+        self.v
+            .iter()
+            .enumerate()
+            .filter(|(_, qi)| qi.norm() > 1e-8)
+            .map(|(i, &qi)| {
+                let bits = format!("{:0>width$b}", i, width = width);
+                format!("{}|{}>", qcoef(qi), &bits[bits.len() - width..])
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Number of basis states with amplitude magnitude above `tol`. A
+    /// support much smaller than 2^n suggests a sparse representation.
+    pub fn support_size(&self, tol: f64) -> usize {
+        self.v.iter().filter(|qi| qi.norm() > tol).count()
+    }
+
+    /// Significant terms as (bitstring, amplitude) pairs in basis order, for
+    /// terms with magnitude above `tol`. Bitstrings are zero-padded to n.
+    pub fn to_terms_vec(&self, tol: f64) -> Vec<(String, Complex64)> {
+        self.v
+            .iter()
+            .enumerate()
+            .filter(|(_, qi)| qi.norm() > tol)
+            .map(|(i, &qi)| (format!("{:0>width$b}", i, width = self.n), qi))
+            .collect()
+    }
+
+    /// Return the `k` largest-magnitude significant terms, largest first.
+    /// Ties are ordered by basis index.
+    pub fn terms_top(&self, k: usize) -> String {
+        let mut idx: Vec<usize> = (0..self.v.len())
+            .filter(|&i| self.v[i].norm() > 1e-8)
+            .collect();
+        idx.sort_by(|&a, &b| self.v[b].norm().total_cmp(&self.v[a].norm()));
+        idx.iter()
+            .take(k)
+            .map(|&i| qterm(i, self.v[i], self.n))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Amplitude of the basis state given as a bitstring, in display order
+    /// (the rightmost character is qubit 0).
+    pub fn amplitude(&self, bits: &str) -> Complex64 {
+        self.v[self.bits_index(bits)]
+    }
+
+    /// Probability |amplitude|^2 of the full outcome given as a bitstring,
+    /// in display order (the rightmost character is qubit 0).
+    pub fn prob(&self, bits: &str) -> f64 {
+        self.amplitude(bits).norm_sqr()
+    }
+
+    /// Convert a length-n bitstring in display order to a basis index.
+    fn bits_index(&self, bits: &str) -> usize {
+        assert!(
+            bits.len() == self.n,
+            "Bitstring '{bits}' has length {}, expected {}",
+            bits.len(),
+            self.n
+        );
+        assert!(
+            bits.chars().all(|c| c == '0' || c == '1'),
+            "Invalid bitstring '{bits}'. Valid: 0, 1"
+        );
+        bits.chars().fold(0, |acc, c| (acc << 1) | (c == '1') as usize)
+    }
+
+    /// LaTeX representation of the state, e.g.
+    /// `\frac{1}{\sqrt{2}}(|00\rangle + |11\rangle)` for a Bell state.
+    ///
+    /// When all significant terms share a magnitude it is factored out in
+    /// front, and magnitudes of the form 1/sqrt(k) are rendered as fractions.
+    pub fn to_latex(&self) -> String {
+        let terms: Vec<(usize, Complex64)> = self
+            .v
+            .iter()
+            .enumerate()
+            .filter(|(_, qi)| qi.norm() > 1e-8)
+            .map(|(i, &qi)| (i, qi))
+            .collect();
+        let r = terms[0].1.norm();
+        let common = terms.iter().all(|(_, qi)| (qi.norm() - r).abs() < 1e-8);
+        let scale = if common { r } else { 1.0 };
+
+        let mut body = String::new();
+        for (j, &(i, qi)) in terms.iter().enumerate() {
+            let (negative, coef) = latex_coef(qi / scale);
+            let sign = match (j, negative) {
+                (0, false) => "",
+                (0, true) => "-",
+                (_, false) => " + ",
+                (_, true) => " - ",
+            };
+            body.push_str(&format!(
+                "{sign}{coef}|{:0>width$b}\\rangle",
+                i,
+                width = self.n
+            ));
+        }
+
+        if common && terms.len() > 1 {
+            format!("{}({body})", latex_magnitude(r))
+        } else {
+            body
+        }
+    }
+
     /// Apply a single-qubit gate matrix to the target qubit.
+    ///
+    /// Amplitude pairs whose combined magnitude is below `SKIP_TOL` are left
+    /// untouched for speed; use `apply1q_exact` when that matters.
     pub fn apply1q(&mut self, m: &Array2<Complex64>, target: usize) -> &mut Self {
+        self.apply1q_tol(m, target, SKIP_TOL)
+    }
+
+    /// Apply a single-qubit gate matrix to every amplitude pair, with no
+    /// small-amplitude skipping, so the update is exactly unitary.
+    pub fn apply1q_exact(&mut self, m: &Array2<Complex64>, target: usize) -> &mut Self {
+        self.apply1q_tol(m, target, 0.0)
+    }
+
+    /// Apply the conjugate transpose of the single-qubit matrix `m` to the
+    /// target qubit, undoing `apply1q(m, target)` for a unitary `m`.
+    pub fn u1q_dag(&mut self, m: &Array2<Complex64>, target: usize) -> &mut Self {
+        self.apply1q(&m.t().mapv(|x| x.conj()), target)
+    }
+
+    /// Single-qubit kernel; pairs with combined magnitude below `skip_tol`
+    /// are skipped (a tolerance of 0 never skips).
+    fn apply1q_tol(&mut self, m: &Array2<Complex64>, target: usize, skip_tol: f64) -> &mut Self {
         assert!(
             target < self.n,
             "Invalid target qubit {target}. Must be in [0, {})",
             self.n
         );
+        debug_assert!(is_unitary(m, 1e-8), "Single-qubit gate matrix is not unitary");
         let m00 = m[[0, 0]];
         let m01 = m[[0, 1]];
         let m10 = m[[1, 0]];
@@ -227,7 +747,7 @@ impl QReg {
         // SAFETY: Each (i, j) pair is unique and non-overlapping.
         // For target bit b, pairs are (i, i|(1<<b)) for all i where bit b is 0.
         // No two iterations touch the same array element.
-        (0..len).into_par_iter().for_each(move |i| {
+        for_each_index(len, move |i| {
             let j = conjugate_index(i, target);
             if i > j {
                 return;
@@ -235,7 +755,7 @@ impl QReg {
             unsafe {
                 let qi = ptr.read(i);
                 let qj = ptr.read(j);
-                if qi.norm() + qj.norm() < 1e-8 {
+                if qi.norm() + qj.norm() < skip_tol {
                     return;
                 }
                 ptr.write(i, m00 * qi + m01 * qj);
@@ -246,11 +766,56 @@ impl QReg {
     }
 
     /// Apply a two-qubit gate matrix to the control and target qubits.
+    ///
+    /// Amplitude groups whose combined magnitude is below `SKIP_TOL` are left
+    /// untouched for speed; use `apply2q_exact` when that matters.
     pub fn apply2q(
         &mut self,
         m: &Array2<Complex64>,
         control: usize,
         target: usize,
+    ) -> &mut Self {
+        self.apply2q_tol(m, control, target, SKIP_TOL)
+    }
+
+    /// Apply a two-qubit gate matrix to every amplitude group, with no
+    /// small-amplitude skipping, so the update is exactly unitary.
+    pub fn apply2q_exact(
+        &mut self,
+        m: &Array2<Complex64>,
+        control: usize,
+        target: usize,
+    ) -> &mut Self {
+        self.apply2q_tol(m, control, target, 0.0)
+    }
+
+    /// Like `apply1q`, but takes the 2x2 matrix as a row-major flat array,
+    /// for callers (e.g. over FFI) without an ndarray `Array2`.
+    pub fn apply1q_flat(&mut self, m: &[Complex64; 4], target: usize) -> &mut Self {
+        let m = Array2::from_shape_vec((2, 2), m.to_vec()).expect("2x2 from 4 elements");
+        self.apply1q(&m, target)
+    }
+
+    /// Like `apply2q`, but takes the 4x4 matrix as a row-major flat array,
+    /// for callers (e.g. over FFI) without an ndarray `Array2`.
+    pub fn apply2q_flat(
+        &mut self,
+        m: &[Complex64; 16],
+        control: usize,
+        target: usize,
+    ) -> &mut Self {
+        let m = Array2::from_shape_vec((4, 4), m.to_vec()).expect("4x4 from 16 elements");
+        self.apply2q(&m, control, target)
+    }
+
+    /// Two-qubit kernel; groups with combined magnitude below `skip_tol`
+    /// are skipped (a tolerance of 0 never skips).
+    fn apply2q_tol(
+        &mut self,
+        m: &Array2<Complex64>,
+        control: usize,
+        target: usize,
+        skip_tol: f64,
     ) -> &mut Self {
         assert!(
             control < self.n,
@@ -263,6 +828,7 @@ impl QReg {
             self.n
         );
         assert!(control != target, "Control and target must be different qubits");
+        debug_assert!(is_unitary(m, 1e-8), "Two-qubit gate matrix is not unitary");
 
         let mv: [[Complex64; 4]; 4] = [
             [m[[0, 0]], m[[0, 1]], m[[0, 2]], m[[0, 3]]],
@@ -275,7 +841,7 @@ impl QReg {
         // SAFETY: Each (i, j, k, l) group is unique and non-overlapping.
         // The two bit positions (control, target) partition all 2^n indices
         // into groups of 4 that don't overlap between iterations.
-        (0..len).into_par_iter().for_each(move |i| {
+        for_each_index(len, move |i| {
             let j = conjugate_index(i, target);
             if i > j {
                 return;
@@ -293,7 +859,7 @@ impl QReg {
                     ptr.read(k),
                     ptr.read(l),
                 );
-                if qi.norm() + qj.norm() + qk.norm() + ql.norm() < 1e-8 {
+                if qi.norm() + qj.norm() + qk.norm() + ql.norm() < skip_tol {
                     return;
                 }
 
@@ -310,63 +876,362 @@ impl QReg {
         self
     }
 
-    /// Check if this quantum state is close to another.
-    pub fn isclose(&self, other: &QReg) -> bool {
-        if self.v.len() != other.v.len() {
-            return false;
-        }
-        self.v
-            .iter()
-            .zip(other.v.iter())
-            .all(|(a, b)| (a - b).norm() < 1e-5)
-    }
-
-    /// Check if this quantum state is close to a slice of f64 values (treated as real).
-    pub fn isclose_slice(&self, other: &[f64]) -> bool {
-        if self.v.len() != other.len() {
-            return false;
-        }
-        self.v
-            .iter()
-            .zip(other.iter())
-            .all(|(a, b)| (a - b).norm() < 1e-5)
-    }
-
-    // ---- Gate methods (consume self for chaining) ----
-
-    /// Apply Pauli-X (NOT) gate to target qubit.
-    pub fn x(mut self, target: usize) -> Self {
-        self.apply1q(&X_GATE, target);
-        self
+    /// Apply a k-qubit gate matrix (2^k x 2^k) to `qubits`. The first qubit
+    /// is the most significant bit of the matrix index, so
+    /// `apply_nq(&CNOT_GATE, &[control, target])` matches `apply2q`.
+    ///
+    /// This allocates a full-size output buffer on every call; in hot loops
+    /// use `apply_nq_with_scratch` to reuse one.
+    pub fn apply_nq(&mut self, m: &Array2<Complex64>, qubits: &[usize]) -> &mut Self {
+        self.apply_nq_with_scratch(m, qubits, &mut Vec::new())
     }
 
-    /// Apply Pauli-Y gate to target qubit.
-    pub fn y(mut self, target: usize) -> Self {
-        self.apply1q(&Y_GATE, target);
-        self
-    }
+    /// Like `apply_nq`, but computes the new amplitudes into `scratch` and
+    /// swaps it with the state vector instead of allocating. After the call
+    /// `scratch` holds the old amplitude buffer, ready for the next gate, so
+    /// a loop applying many gates with the same scratch allocates only once:
+    ///
+    /// ```
+    /// # use rvecsim::{zeros, CNOT_GATE};
+    /// let mut q = zeros(4);
+    /// let mut scratch = Vec::new();
+    /// for c in 0..3 {
+    ///     q.apply_nq_with_scratch(&CNOT_GATE, &[c, c + 1], &mut scratch);
+    /// }
+    /// ```
+    pub fn apply_nq_with_scratch(
+        &mut self,
+        m: &Array2<Complex64>,
+        qubits: &[usize],
+        scratch: &mut Vec<Complex64>,
+    ) -> &mut Self {
+        self.check_qubit_set(qubits);
+        let k = qubits.len();
+        assert!(
+            m.dim() == (1 << k, 1 << k),
+            "Gate matrix is {:?}, expected {}x{} for {} qubits",
+            m.dim(),
+            1 << k,
+            1 << k,
+            k
+        );
+        debug_assert!(is_unitary(m, 1e-8), "Gate matrix is not unitary");
 
-    /// Apply Pauli-Z gate to target qubit.
-    pub fn z(mut self, target: usize) -> Self {
-        self.apply1q(&Z_GATE, target);
-        self
-    }
+        // offsets[c] sets the state bits encoding matrix column c
+        let offsets: Vec<usize> = (0..1usize << k)
+            .map(|c| {
+                (0..k)
+                    .filter(|&j| (c >> (k - 1 - j)) & 1 == 1)
+                    .fold(0, |acc, j| acc | (1 << qubits[j]))
+            })
+            .collect();
+        let mask = offsets[(1 << k) - 1];
+        let row_of = |i: usize| {
+            qubits
+                .iter()
+                .fold(0, |acc, &q| (acc << 1) | ((i >> q) & 1))
+        };
 
-    /// Apply Hadamard gate to target qubit.
-    pub fn h(mut self, target: usize) -> Self {
-        self.apply1q(&H_GATE, target);
-        self
-    }
+        let len = self.v.len();
+        scratch.clear();
+        scratch.resize(len, ZERO);
+        let out = SendPtr(scratch.as_mut_ptr());
+        let v = &self.v;
+        // SAFETY: Each iteration writes only its own index of `scratch`.
+        for_each_index(len, |i| {
+            let r = row_of(i);
+            let base = i & !mask;
+            let amp = offsets
+                .iter()
+                .enumerate()
+                .map(|(c, &off)| m[[r, c]] * v[base | off])
+                .sum();
+            unsafe { out.write(i, amp) };
+        });
 
-    /// Apply S (phase) gate to target qubit.
-    pub fn s(mut self, target: usize) -> Self {
-        self.apply1q(&S_GATE, target);
+        let new_v = Array1::from_vec(std::mem::take(scratch));
+        let (old, _) = std::mem::replace(&mut self.v, new_v).into_raw_vec_and_offset();
+        *scratch = old;
         self
     }
 
-    /// Apply controlled-NOT gate.
-    pub fn cnot(mut self, control: usize, target: usize) -> Self {
-        self.apply2q(&CNOT_GATE, control, target);
+    /// Apply the single-qubit matrix `m` to `target` only in the subspace
+    /// where `control` is 1. With `X_GATE` this is a CNOT; with `H_GATE` or
+    /// a rotation it gives CH, CRY, CRZ and so on without a 4x4 matrix.
+    pub fn controlled_1q(
+        &mut self,
+        m: &Array2<Complex64>,
+        control: usize,
+        target: usize,
+    ) -> &mut Self {
+        assert!(
+            control < self.n,
+            "Invalid control qubit {control}. Must be in [0, {})",
+            self.n
+        );
+        assert!(
+            target < self.n,
+            "Invalid target qubit {target}. Must be in [0, {})",
+            self.n
+        );
+        assert!(control != target, "Control and target must be different qubits");
+        debug_assert!(is_unitary(m, 1e-8), "Single-qubit gate matrix is not unitary");
+        let (m00, m01, m10, m11) = (m[[0, 0]], m[[0, 1]], m[[1, 0]], m[[1, 1]]);
+        let len = self.v.len();
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each (i, j) pair differs only in the target bit and is
+        // visited once, from the index with the target bit clear.
+        for_each_index(len, move |i| {
+            let j = conjugate_index(i, target);
+            if i > j || (i >> control) & 1 == 0 {
+                return;
+            }
+            unsafe {
+                let (qi, qj) = (ptr.read(i), ptr.read(j));
+                ptr.write(i, m00 * qi + m01 * qj);
+                ptr.write(j, m10 * qi + m11 * qj);
+            }
+        });
+        self
+    }
+
+    /// Apply a multi-controlled X gate: flip `target` when every qubit in
+    /// `controls` is 1. With no controls this is a plain X gate.
+    pub fn mcx(&mut self, controls: &[usize], target: usize) -> &mut Self {
+        assert!(
+            target < self.n,
+            "Invalid target qubit {target}. Must be in [0, {})",
+            self.n
+        );
+        for (j, &c) in controls.iter().enumerate() {
+            assert!(
+                c < self.n,
+                "Invalid control qubit {c}. Must be in [0, {})",
+                self.n
+            );
+            assert!(c != target, "Control and target must be different qubits");
+            assert!(!controls[..j].contains(&c), "Duplicate control qubit {c}");
+        }
+
+        let mask = controls.iter().fold(0usize, |m, &c| m | (1 << c));
+        let len = self.v.len();
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each (i, j) pair differs only in the target bit and is
+        // visited once, from the index with the target bit clear.
+        for_each_index(len, move |i| {
+            let j = conjugate_index(i, target);
+            if i > j || i & mask != mask {
+                return;
+            }
+            unsafe {
+                let (qi, qj) = (ptr.read(i), ptr.read(j));
+                ptr.write(i, qj);
+                ptr.write(j, qi);
+            }
+        });
+        self
+    }
+
+    /// Apply a Fredkin (controlled-SWAP) gate: swap qubits `a` and `b`
+    /// when `control` is 1.
+    pub fn cswap(&mut self, control: usize, a: usize, b: usize) -> &mut Self {
+        for (name, q) in [("control", control), ("swap", a), ("swap", b)] {
+            assert!(
+                q < self.n,
+                "Invalid {name} qubit {q}. Must be in [0, {})",
+                self.n
+            );
+        }
+        assert!(
+            control != a && control != b && a != b,
+            "Control and swap qubits must be distinct"
+        );
+
+        let (cbit, abit, bbit) = (1usize << control, 1usize << a, 1usize << b);
+        let len = self.v.len();
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each group of 8 indices sharing the other bits contains one
+        // pair to swap, |c=1,a=1,b=0> <-> |c=1,a=0,b=1>, visited once from
+        // the first member. No two iterations touch the same element.
+        for_each_index(len, move |i| {
+            if i & cbit == 0 || i & abit == 0 || i & bbit != 0 {
+                return;
+            }
+            let j = i ^ abit ^ bbit;
+            unsafe {
+                let (qi, qj) = (ptr.read(i), ptr.read(j));
+                ptr.write(i, qj);
+                ptr.write(j, qi);
+            }
+        });
+        self
+    }
+
+    /// Check if this quantum state is close to another.
+    pub fn isclose(&self, other: &QReg) -> bool {
+        if self.v.len() != other.v.len() {
+            return false;
+        }
+        self.v
+            .iter()
+            .zip(other.v.iter())
+            .all(|(a, b)| (a - b).norm() < 1e-5)
+    }
+
+    /// Check if this quantum state is close to a slice of f64 values (treated as real).
+    pub fn isclose_slice(&self, other: &[f64]) -> bool {
+        if self.v.len() != other.len() {
+            return false;
+        }
+        self.v
+            .iter()
+            .zip(other.iter())
+            .all(|(a, b)| (a - b).norm() < 1e-5)
+    }
+
+    /// Check if this state equals another up to a global phase,
+    /// i.e. |<self|other>| is close to 1.
+    pub fn isclose_up_to_phase(&self, other: &QReg) -> bool {
+        if self.v.len() != other.v.len() {
+            return false;
+        }
+        (self.inner(other).norm() - 1.0).abs() < 1e-5
+    }
+
+    /// The normalized linear combination a|self> + b|other>. Unlike `+`,
+    /// which always uses 1/sqrt(2) weights, `a` and `b` are arbitrary.
+    pub fn add_weighted(&self, a: Complex64, other: &QReg, b: Complex64) -> QReg {
+        assert!(
+            self.v.len() == other.v.len(),
+            "States must have the same length, got {} and {}",
+            self.v.len(),
+            other.v.len()
+        );
+        let mut v = self.v.mapv(|x| x * a);
+        v.scaled_add(b, &other.v);
+        QReg::from_array(v)
+    }
+
+    /// Complex conjugate |psi*> of the state, with every amplitude
+    /// conjugated in the computational basis. This is antiunitary (time
+    /// reversal), so it is not a gate; it appears in e.g. the concurrence.
+    pub fn conjugate(&self) -> QReg {
+        QReg {
+            v: self.v.mapv(|x| x.conj()),
+            n: self.n,
+        }
+    }
+
+    /// Inner product <self|other>.
+    pub fn inner(&self, other: &QReg) -> Complex64 {
+        assert!(
+            self.v.len() == other.v.len(),
+            "States must have the same length, got {} and {}",
+            self.v.len(),
+            other.v.len()
+        );
+        self.v
+            .iter()
+            .zip(other.v.iter())
+            .map(|(a, b)| a.conj() * b)
+            .sum()
+    }
+
+    /// Overlaps <b_i|self> with each state in `basis`, e.g. for projecting
+    /// onto a subspace. All states must have the same length as `self`.
+    pub fn overlaps(&self, basis: &[QReg]) -> Vec<Complex64> {
+        basis.iter().map(|b| b.inner(self)).collect()
+    }
+
+    /// Fidelity |<self|other>|^2 between two pure states.
+    pub fn fidelity(&self, other: &QReg) -> f64 {
+        self.inner(other).norm_sqr()
+    }
+
+    /// Trace distance between two pure states, sqrt(1 - |<self|other>|^2):
+    /// 0 for states equal up to a global phase, 1 for orthogonal states.
+    pub fn trace_distance(&self, other: &QReg) -> f64 {
+        // Clamp so rounding cannot push the fidelity above 1
+        (1.0 - self.fidelity(other).min(1.0)).sqrt()
+    }
+
+    // ---- Gate methods (consume self for chaining) ----
+
+    /// Apply Pauli-X (NOT) gate to target qubit.
+    pub fn x(mut self, target: usize) -> Self {
+        self.apply1q(&X_GATE, target);
+        self
+    }
+
+    /// Apply Pauli-Y gate to target qubit.
+    pub fn y(mut self, target: usize) -> Self {
+        self.apply1q(&Y_GATE, target);
+        self
+    }
+
+    /// Apply Pauli-Z gate to target qubit.
+    pub fn z(mut self, target: usize) -> Self {
+        self.apply1q(&Z_GATE, target);
+        self
+    }
+
+    /// Apply Hadamard gate to target qubit.
+    pub fn h(mut self, target: usize) -> Self {
+        self.apply1q(&H_GATE, target);
+        self
+    }
+
+    /// Apply S (phase) gate to target qubit.
+    pub fn s(mut self, target: usize) -> Self {
+        self.apply1q(&S_GATE, target);
+        self
+    }
+
+    /// Apply S-dagger (inverse phase) gate to target qubit.
+    pub fn sdg(mut self, target: usize) -> Self {
+        self.apply1q(&SDG_GATE, target);
+        self
+    }
+
+    /// Apply T (pi/8) gate to target qubit.
+    pub fn t(mut self, target: usize) -> Self {
+        self.apply1q(&T_GATE, target);
+        self
+    }
+
+    /// Apply T-dagger gate to target qubit.
+    pub fn tdg(mut self, target: usize) -> Self {
+        self.apply1q(&TDG_GATE, target);
+        self
+    }
+
+    /// Apply a Z-rotation by angle `theta` (radians) to target qubit.
+    pub fn rz(mut self, theta: f64, target: usize) -> Self {
+        self.apply1q(&rz_gate(theta), target);
+        self
+    }
+
+    /// Apply an X-rotation by angle `theta` (radians) to target qubit.
+    pub fn rx(mut self, theta: f64, target: usize) -> Self {
+        self.apply1q(&rx_gate(theta), target);
+        self
+    }
+
+    /// Apply a Y-rotation by angle `theta` (radians) to target qubit.
+    pub fn ry(mut self, theta: f64, target: usize) -> Self {
+        self.apply1q(&ry_gate(theta), target);
+        self
+    }
+
+    /// Apply a phase gate P(phi) = diag(1, e^{i phi}) to target qubit.
+    pub fn p(mut self, phi: f64, target: usize) -> Self {
+        self.apply1q(&p_gate(phi), target);
+        self
+    }
+
+    /// Apply controlled-NOT gate.
+    pub fn cnot(mut self, control: usize, target: usize) -> Self {
+        self.apply2q(&CNOT_GATE, control, target);
         self
     }
 
@@ -376,353 +1241,3103 @@ impl QReg {
         self
     }
 
-    /// Measure qubit `i` `ntimes` times, collapsing the state each time.
-    pub fn measure(&mut self, i: usize, ntimes: usize, rng: &mut impl Rng) -> Vec<usize> {
-        assert!(i < self.n, "Invalid qubit {i}. Must be in [0, {})", self.n);
+    /// Apply a single-qubit gate matrix to every qubit 0..n.
+    pub fn apply1q_all(&mut self, m: &Array2<Complex64>) -> &mut Self {
+        for target in 0..self.n {
+            self.apply1q(m, target);
+        }
+        self
+    }
 
-        let mut results = Vec::with_capacity(ntimes);
-        for _ in 0..ntimes {
-            // Calculate probability of measuring |0> on qubit i
-            let prob0: f64 = self
-                .v
-                .iter()
-                .enumerate()
-                .filter(|(idx, _)| (idx >> i) & 1 == 0)
-                .map(|(_, amp)| amp.norm_sqr())
-                .sum();
+    /// Apply a single-qubit gate matrix to each qubit in `targets`, in order.
+    ///
+    /// Runs of distinct targets are fused in groups of up to four, so each
+    /// group costs one pass over the state instead of one per target. A
+    /// repeated target starts a new group, keeping the result identical to
+    /// calling `apply1q_exact` for each target in turn. The fused kernel
+    /// never skips near-zero pairs, so it does not apply `SKIP_TOL` the way
+    /// `apply1q` does.
+    pub fn apply1q_many(&mut self, m: &Array2<Complex64>, targets: &[usize]) -> &mut Self {
+        const MAX_GROUP: usize = 4;
+        for &t in targets {
+            assert!(t < self.n, "Invalid target qubit {t}. Must be in [0, {})", self.n);
+        }
+        debug_assert!(is_unitary(m, 1e-8), "Single-qubit gate matrix is not unitary");
+        let (m00, m01, m10, m11) = (m[[0, 0]], m[[0, 1]], m[[1, 0]], m[[1, 1]]);
 
-            let outcome = if rng.r#gen::<f64>() < prob0 { 0 } else { 1 };
-            results.push(outcome);
+        let mut rest = targets;
+        while !rest.is_empty() {
+            let mut k = 1;
+            while k < rest.len().min(MAX_GROUP) && !rest[..k].contains(&rest[k]) {
+                k += 1;
+            }
+            let (group, tail) = rest.split_at(k);
+            rest = tail;
 
-            // Collapse: zero out amplitudes inconsistent with outcome
-            for idx in 0..self.v.len() {
-                if (idx >> i) & 1 != outcome {
-                    self.v[idx] = Complex64::new(0.0, 0.0);
-                }
+            // Offset of local index s (bit b set means group[b] is 1)
+            let mask = group.iter().fold(0usize, |acc, &t| acc | (1 << t));
+            let mut offsets = [0usize; 1 << MAX_GROUP];
+            for (s, off) in offsets.iter_mut().enumerate().take(1 << k) {
+                *off = group
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (b, &t)| acc | (((s >> b) & 1) << t));
             }
-            self.normalize();
+            let len = self.v.len();
+            let ptr = SendPtr(self.v.as_mut_ptr());
+            // SAFETY: Each group of 2^k indices is visited once, from its
+            // member with every target bit clear; groups don't overlap.
+            for_each_index(len, move |i| {
+                if i & mask != 0 {
+                    return;
+                }
+                let mut buf = [ZERO; 1 << MAX_GROUP];
+                for s in 0..1 << k {
+                    buf[s] = unsafe { ptr.read(i | offsets[s]) };
+                }
+                for b in 0..k {
+                    for s in (0..1 << k).filter(|s| s & (1 << b) == 0) {
+                        let (a0, a1) = (buf[s], buf[s | 1 << b]);
+                        buf[s] = m00 * a0 + m01 * a1;
+                        buf[s | 1 << b] = m10 * a0 + m11 * a1;
+                    }
+                }
+                for s in 0..1 << k {
+                    unsafe { ptr.write(i | offsets[s], buf[s]) };
+                }
+            });
         }
-        results
+        self
     }
-}
 
-impl fmt::Display for QReg {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.terms())
+    /// Apply Hadamard gate to every qubit, e.g. |00..0> to the uniform superposition.
+    pub fn h_all(mut self) -> Self {
+        self.apply1q_all(&H_GATE);
+        self
     }
-}
 
-impl Add for QReg {
-    type Output = QReg;
-    fn add(self, other: QReg) -> QReg {
-        let inv_sqrt2 = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
-        let v = (&self.v + &other.v).mapv(|x| x * inv_sqrt2);
-        QReg::from_array(v)
+    /// Apply Pauli-X gate to every qubit.
+    pub fn x_all(mut self) -> Self {
+        self.apply1q_all(&X_GATE);
+        self
+    }
+
+    /// Apply Pauli-Y gate to every qubit.
+    pub fn y_all(mut self) -> Self {
+        self.apply1q_all(&Y_GATE);
+        self
+    }
+
+    /// Apply Pauli-Z gate to every qubit.
+    pub fn z_all(mut self) -> Self {
+        self.apply1q_all(&Z_GATE);
+        self
+    }
+
+    // ---- In-place gate methods (borrow self mutably for chaining) ----
+
+    /// Apply Pauli-X (NOT) gate to target qubit in place.
+    pub fn x_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&X_GATE, target)
+    }
+
+    /// Apply Pauli-Y gate to target qubit in place.
+    pub fn y_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&Y_GATE, target)
+    }
+
+    /// Apply Pauli-Z gate to target qubit in place.
+    pub fn z_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&Z_GATE, target)
+    }
+
+    /// Apply Hadamard gate to target qubit in place.
+    pub fn h_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&H_GATE, target)
+    }
+
+    /// Apply S (phase) gate to target qubit in place.
+    pub fn s_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&S_GATE, target)
+    }
+
+    /// Apply S-dagger (inverse phase) gate to target qubit in place.
+    pub fn sdg_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&SDG_GATE, target)
+    }
+
+    /// Apply T (pi/8) gate to target qubit in place.
+    pub fn t_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&T_GATE, target)
+    }
+
+    /// Apply T-dagger gate to target qubit in place.
+    pub fn tdg_mut(&mut self, target: usize) -> &mut Self {
+        self.apply1q(&TDG_GATE, target)
+    }
+
+    /// Apply a Z-rotation by angle `theta` (radians) to target qubit in place.
+    pub fn rz_mut(&mut self, theta: f64, target: usize) -> &mut Self {
+        self.apply1q(&rz_gate(theta), target)
+    }
+
+    /// Apply an X-rotation by angle `theta` (radians) to target qubit in place.
+    pub fn rx_mut(&mut self, theta: f64, target: usize) -> &mut Self {
+        self.apply1q(&rx_gate(theta), target)
+    }
+
+    /// Apply a Y-rotation by angle `theta` (radians) to target qubit in place.
+    pub fn ry_mut(&mut self, theta: f64, target: usize) -> &mut Self {
+        self.apply1q(&ry_gate(theta), target)
+    }
+
+    /// Apply a phase gate P(phi) to target qubit in place.
+    pub fn p_mut(&mut self, phi: f64, target: usize) -> &mut Self {
+        self.apply1q(&p_gate(phi), target)
+    }
+
+    /// Apply controlled-NOT gate in place.
+    pub fn cnot_mut(&mut self, control: usize, target: usize) -> &mut Self {
+        self.apply2q(&CNOT_GATE, control, target)
+    }
+
+    /// Apply controlled-phase gate in place.
+    pub fn cphase_mut(&mut self, control: usize, target: usize) -> &mut Self {
+        self.apply2q(&CPHASE_GATE, control, target)
+    }
+
+    /// Apply a Givens rotation by `theta` between qubits `a` and `b`,
+    /// mixing |01> and |10> (with `a` as the high bit) and conserving the
+    /// number of excitations. See `givens_gate`.
+    pub fn givens(&mut self, theta: f64, a: usize, b: usize) -> &mut Self {
+        self.apply2q(&givens_gate(theta), a, b)
+    }
+
+    /// Apply a tensor product of Paulis given as a length-n string of
+    /// I, X, Y and Z, in display order (the rightmost character acts on
+    /// qubit 0), so "XZ" applies Z to qubit 0 and X to qubit 1.
+    pub fn apply_pauli(&mut self, paulis: &str) -> &mut Self {
+        self.check_pauli_string(paulis);
+        for (q, p) in paulis.chars().rev().enumerate() {
+            let gate: &Array2<Complex64> = match p {
+                'X' => &X_GATE,
+                'Y' => &Y_GATE,
+                'Z' => &Z_GATE,
+                _ => continue,
+            };
+            self.apply1q(gate, q);
+        }
+        self
+    }
+
+    /// Evolve under a Pauli string: apply exp(-i theta/2 P), where `paulis`
+    /// uses the same display order as `apply_pauli` (rightmost character is
+    /// qubit 0). Each X or Y is rotated onto Z, the parity of the active
+    /// qubits is collected onto the highest one with a CNOT ladder, RZ(theta)
+    /// is applied there, and the ladder and basis changes are undone.
+    pub fn pauli_rotation(&mut self, paulis: &str, theta: f64) -> &mut Self {
+        self.check_pauli_string(paulis);
+        let active: Vec<(usize, char)> = paulis
+            .chars()
+            .rev()
+            .enumerate()
+            .filter(|&(_, p)| p != 'I')
+            .collect();
+
+        let Some(&(last, _)) = active.last() else {
+            // exp(-i theta/2 I) is a global phase
+            let phase = Complex64::from_polar(1.0, -theta / 2.0);
+            self.v.mapv_inplace(|x| x * phase);
+            return self;
+        };
+
+        for &(q, p) in &active {
+            match p {
+                'X' => {
+                    self.h_mut(q);
+                }
+                'Y' => {
+                    self.sdg_mut(q).h_mut(q);
+                }
+                _ => {}
+            }
+        }
+        for pair in active.windows(2) {
+            self.cnot_mut(pair[0].0, pair[1].0);
+        }
+        self.rz_mut(theta, last);
+        for pair in active.windows(2).rev() {
+            self.cnot_mut(pair[0].0, pair[1].0);
+        }
+        for &(q, p) in &active {
+            match p {
+                'X' => {
+                    self.h_mut(q);
+                }
+                'Y' => {
+                    self.h_mut(q).s_mut(q);
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Assert that `paulis` is a length-n string of I, X, Y and Z.
+    fn check_pauli_string(&self, paulis: &str) {
+        assert!(
+            paulis.chars().count() == self.n,
+            "Pauli string '{paulis}' has length {}, expected {}",
+            paulis.chars().count(),
+            self.n
+        );
+        if let Some(p) = paulis.chars().find(|p| !"IXYZ".contains(*p)) {
+            panic!("Invalid Pauli '{p}' in '{paulis}'. Valid: I, X, Y, Z");
+        }
+    }
+
+    /// Apply a gate by name (case-insensitive) to `qubits`, e.g.
+    /// `apply_named("cnot", &[0, 1])`. Supported: x, y, z, h, s, sdg, t, tdg
+    /// on one qubit; cnot/cx and cz/cphase on (control, target); ccx/toffoli
+    /// on (control, control, target) and cswap on (control, a, b).
+    /// Returns an error for an unknown name or invalid qubits.
+    pub fn apply_named(&mut self, name: &str, qubits: &[usize]) -> Result<&mut Self, String> {
+        let name = name.to_ascii_lowercase();
+        let arity = match name.as_str() {
+            "x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" => 1,
+            "cnot" | "cx" | "cz" | "cphase" => 2,
+            "ccx" | "toffoli" | "cswap" => 3,
+            _ => return Err(format!("Unknown gate '{name}'")),
+        };
+        if qubits.len() != arity {
+            return Err(format!(
+                "Gate '{name}' acts on {arity} qubit(s), got {}",
+                qubits.len()
+            ));
+        }
+        for (j, &q) in qubits.iter().enumerate() {
+            if q >= self.n {
+                return Err(format!("Invalid qubit {q}. Must be in [0, {})", self.n));
+            }
+            if qubits[..j].contains(&q) {
+                return Err(format!("Duplicate qubit {q}"));
+            }
+        }
+        Ok(match (name.as_str(), qubits) {
+            ("x", &[t]) => self.x_mut(t),
+            ("y", &[t]) => self.y_mut(t),
+            ("z", &[t]) => self.z_mut(t),
+            ("h", &[t]) => self.h_mut(t),
+            ("s", &[t]) => self.s_mut(t),
+            ("sdg", &[t]) => self.sdg_mut(t),
+            ("t", &[t]) => self.t_mut(t),
+            ("tdg", &[t]) => self.tdg_mut(t),
+            ("cnot" | "cx", &[c, t]) => self.cnot_mut(c, t),
+            ("cz" | "cphase", &[c, t]) => self.cphase_mut(c, t),
+            ("ccx" | "toffoli", &[c0, c1, t]) => self.mcx(&[c0, c1], t),
+            ("cswap", &[c, a, b]) => self.cswap(c, a, b),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Apply a gate given as a `Gate` value, dispatching to the matching
+    /// gate method.
+    pub fn apply_gate(&mut self, g: &Gate) -> &mut Self {
+        match *g {
+            Gate::X { target } => self.x_mut(target),
+            Gate::Y { target } => self.y_mut(target),
+            Gate::Z { target } => self.z_mut(target),
+            Gate::H { target } => self.h_mut(target),
+            Gate::S { target } => self.s_mut(target),
+            Gate::Sdg { target } => self.sdg_mut(target),
+            Gate::T { target } => self.t_mut(target),
+            Gate::Tdg { target } => self.tdg_mut(target),
+            Gate::Rz { theta, target } => self.rz_mut(theta, target),
+            Gate::Cnot { control, target } => self.cnot_mut(control, target),
+            Gate::Cphase { control, target } => self.cphase_mut(control, target),
+        }
+    }
+
+    /// Apply a sequence of gates in order via `apply_gate`.
+    pub fn run(&mut self, gates: &[Gate]) -> &mut Self {
+        for g in gates {
+            self.apply_gate(g);
+        }
+        self
+    }
+
+    // ---- Algorithms ----
+
+    /// Apply the Quantum Fourier Transform across all qubits.
+    ///
+    /// Maps |j> to sum_k e^{2 pi i jk / 2^n} |k> / sqrt(2^n), using the
+    /// H + controlled-phase ladder followed by qubit-reversing swaps.
+    pub fn qft(mut self) -> Self {
+        for j in (0..self.n).rev() {
+            self.apply1q(&H_GATE, j);
+            for k in (0..j).rev() {
+                let phi = std::f64::consts::PI / (1u64 << (j - k)) as f64;
+                self.apply2q(&cp_gate(phi), k, j);
+            }
+        }
+        for i in 0..self.n / 2 {
+            self.swap_qubits(i, self.n - 1 - i);
+        }
+        self
+    }
+
+    /// Apply the inverse Quantum Fourier Transform across all qubits.
+    pub fn iqft(mut self) -> Self {
+        for i in 0..self.n / 2 {
+            self.swap_qubits(i, self.n - 1 - i);
+        }
+        for j in 0..self.n {
+            for k in 0..j {
+                let phi = -std::f64::consts::PI / (1u64 << (j - k)) as f64;
+                self.apply2q(&cp_gate(phi), k, j);
+            }
+            self.apply1q(&H_GATE, j);
+        }
+        self
+    }
+
+    /// Apply the Grover diffusion operator 2|s><s| - I, where |s> is the
+    /// uniform superposition (inversion about the mean).
+    pub fn diffusion(mut self) -> Self {
+        for q in 0..self.n {
+            self.apply1q(&H_GATE, q);
+        }
+        // 2|0><0| - I: keep |0...0>, negate everything else
+        self.v.mapv_inplace(|x| -x);
+        self.v[0] = -self.v[0];
+        for q in 0..self.n {
+            self.apply1q(&H_GATE, q);
+        }
+        self
+    }
+
+    /// Estimate Re<psi|U|psi> (or Im, with `imaginary`) by the Hadamard
+    /// test, where U is the gate sequence `u` acting on this state. An
+    /// ancilla is added as the new high qubit, put in |+> (rotated by S†
+    /// for the imaginary part), used to control U, then measured in the X
+    /// basis `nshots` times; the estimate is P(0) - P(1).
+    pub fn hadamard_test(
+        &self,
+        u: &[Gate],
+        imaginary: bool,
+        rng: &mut impl Rng,
+        nshots: usize,
+    ) -> f64 {
+        assert!(nshots > 0, "Hadamard test requires at least one shot");
+        let anc = self.n;
+        let mut q = zeros(1) * self.clone();
+        q.h_mut(anc);
+        if imaginary {
+            q.sdg_mut(anc);
+        }
+        for g in u {
+            let qubits = g.qubits();
+            for &t in &qubits {
+                assert!(t < self.n, "Invalid qubit {t}. Must be in [0, {})", self.n);
+            }
+            let m = g.matrix();
+            if let [target] = qubits[..] {
+                q.controlled_1q(&m, anc, target);
+            } else {
+                // Controlled-U on (ancilla, control, target): identity on
+                // the ancilla=0 block, U on the ancilla=1 block
+                let mut cm = Array2::eye(8);
+                cm.slice_mut(ndarray::s![4.., 4..]).assign(&m);
+                q.apply_nq(&cm, &[anc, qubits[0], qubits[1]]);
+            }
+        }
+        q.h_mut(anc);
+        let ones: usize = q.sample(anc, nshots, rng).iter().sum();
+        (nshots as f64 - 2.0 * ones as f64) / nshots as f64
+    }
+
+    /// Swap two qubits using three CNOTs.
+    fn swap_qubits(&mut self, a: usize, b: usize) {
+        self.apply2q(&CNOT_GATE, a, b);
+        self.apply2q(&CNOT_GATE, b, a);
+        self.apply2q(&CNOT_GATE, a, b);
+    }
+
+    /// Measure qubit `i` `ntimes` times, collapsing the state each time.
+    pub fn measure(&mut self, i: usize, ntimes: usize, rng: &mut impl Rng) -> Vec<usize> {
+        assert!(i < self.n, "Invalid qubit {i}. Must be in [0, {})", self.n);
+
+        // Probability of measuring |0> on qubit i. After the first collapse
+        // the qubit is in a definite state, so this becomes exactly 0 or 1
+        // and later shots need no pass over the amplitudes.
+        let mut prob0 = self.prob0(i);
+        let mut collapsed = false;
+
+        let mut results = Vec::with_capacity(ntimes);
+        for _ in 0..ntimes {
+            let outcome = if rng.r#gen::<f64>() < prob0 { 0 } else { 1 };
+            results.push(outcome);
+
+            if !collapsed {
+                let prob = if outcome == 0 { prob0 } else { 1.0 - prob0 };
+                self.collapse(i, outcome, prob);
+                prob0 = if outcome == 0 { 1.0 } else { 0.0 };
+                collapsed = true;
+            }
+        }
+        results
+    }
+
+    /// Measure `qubit` once, collapsing the state, and record the outcome
+    /// in classical bit `cbit` of `creg`. Returns the outcome.
+    pub fn measure_into(
+        &mut self,
+        qubit: usize,
+        cbit: usize,
+        creg: &mut ClassicalReg,
+        rng: &mut impl Rng,
+    ) -> usize {
+        assert!(
+            cbit < creg.len(),
+            "Invalid classical bit {cbit}. Must be in [0, {})",
+            creg.len()
+        );
+        let outcome = self.measure(qubit, 1, rng)[0];
+        creg.set(cbit, outcome);
+        outcome
+    }
+
+    /// Apply X to `qubit` only if classical bit `cbit` of `creg` holds
+    /// `cbit_value`. Panics if the bit has not been written yet.
+    pub fn x_if(
+        &mut self,
+        qubit: usize,
+        cbit_value: usize,
+        creg: &ClassicalReg,
+        cbit: usize,
+    ) -> &mut Self {
+        if classical_bit(creg, cbit) == cbit_value {
+            self.x_mut(qubit);
+        }
+        self
+    }
+
+    /// Apply Z to `qubit` only if classical bit `cbit` of `creg` holds
+    /// `cbit_value`. Panics if the bit has not been written yet.
+    pub fn z_if(
+        &mut self,
+        qubit: usize,
+        cbit_value: usize,
+        creg: &ClassicalReg,
+        cbit: usize,
+    ) -> &mut Self {
+        if classical_bit(creg, cbit) == cbit_value {
+            self.z_mut(qubit);
+        }
+        self
+    }
+
+    /// Measure `qubit` in the 'x', 'y' or 'z' basis `ntimes` times,
+    /// collapsing onto an eigenstate of that Pauli. Outcome 0 is the +1
+    /// eigenstate (|0>, |+> or |i>) and 1 the -1 eigenstate.
+    pub fn measure_basis(
+        &mut self,
+        qubit: usize,
+        basis: char,
+        ntimes: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<usize> {
+        assert!(qubit < self.n, "Invalid qubit {qubit}. Must be in [0, {})", self.n);
+        // Rotate the measurement basis onto Z, measure, and rotate back
+        let (to_z, from_z): (&[&Array2<Complex64>], &[&Array2<Complex64>]) = match basis {
+            'x' => (&[&H_GATE], &[&H_GATE]),
+            'y' => (&[&SDG_GATE, &H_GATE], &[&H_GATE, &S_GATE]),
+            'z' => (&[], &[]),
+            _ => panic!("Invalid measurement basis '{basis}'. Valid: x, y, z"),
+        };
+        for m in to_z {
+            self.apply1q(m, qubit);
+        }
+        let results = self.measure(qubit, ntimes, rng);
+        for m in from_z {
+            self.apply1q(m, qubit);
+        }
+        results
+    }
+
+    /// Project `qubit` onto `outcome` (0 or 1) and renormalize, keeping only
+    /// that branch. Returns the probability of the branch before projection.
+    /// Panics if the branch has (near) zero probability.
+    pub fn project(&mut self, qubit: usize, outcome: usize) -> f64 {
+        assert!(qubit < self.n, "Invalid qubit {qubit}. Must be in [0, {})", self.n);
+        assert!(outcome <= 1, "Outcome must be 0 or 1, got {outcome}");
+
+        let prob0 = self.prob0(qubit);
+        let prob = if outcome == 0 { prob0 } else { 1.0 - prob0 };
+        assert!(
+            prob > 1e-10,
+            "Cannot project qubit {qubit} onto {outcome}: branch has zero probability"
+        );
+        self.collapse(qubit, outcome, prob);
+        prob
+    }
+
+    /// Measure `qubit` with a forced `outcome` instead of sampling, e.g. to
+    /// replay a recorded run. Collapses and renormalizes like `project`,
+    /// but returns self for chaining. Panics if the outcome is impossible.
+    pub fn measure_forced(&mut self, qubit: usize, outcome: usize) -> &mut Self {
+        self.project(qubit, outcome);
+        self
+    }
+
+    /// Sample qubit `qubit` `nshots` times without collapsing the state.
+    /// Each shot is drawn independently from the qubit's marginal distribution.
+    pub fn sample(&self, qubit: usize, nshots: usize, rng: &mut impl Rng) -> Vec<usize> {
+        assert!(qubit < self.n, "Invalid qubit {qubit}. Must be in [0, {})", self.n);
+
+        let prob0 = self.prob0(qubit);
+        (0..nshots)
+            .map(|_| if rng.r#gen::<f64>() < prob0 { 0 } else { 1 })
+            .collect()
+    }
+
+    /// Histogram of `nshots` non-collapsing samples of `qubit`, as
+    /// (#zeros, #ones). The marginal is computed once and each shot is a
+    /// single Bernoulli draw; the counts match `sample` for the same rng.
+    pub fn cdf_sample(&self, qubit: usize, nshots: usize, rng: &mut impl Rng) -> (usize, usize) {
+        assert!(qubit < self.n, "Invalid qubit {qubit}. Must be in [0, {})", self.n);
+
+        let prob0 = self.prob0(qubit);
+        let zeros = (0..nshots).filter(|_| rng.r#gen::<f64>() < prob0).count();
+        (zeros, nshots - zeros)
+    }
+
+    /// Sample the full register `nshots` times without collapsing the state.
+    /// Returns a map from bitstrings (zero-padded to n) to observed counts.
+    pub fn sample_counts(&self, nshots: usize, rng: &mut impl Rng) -> HashMap<String, usize> {
+        // Cumulative distribution over basis states, searched once per shot
+        let cdf: Vec<f64> = self
+            .v
+            .iter()
+            .scan(0.0, |acc, amp| {
+                *acc += amp.norm_sqr();
+                Some(*acc)
+            })
+            .collect();
+        // Draws past a total that rounds just below 1 map to the last state
+        // with nonzero probability, never to a zero-amplitude one
+        let last = self
+            .v
+            .iter()
+            .rposition(|amp| amp.norm_sqr() > 0.0)
+            .unwrap_or(cdf.len() - 1);
+
+        let mut counts = HashMap::new();
+        for _ in 0..nshots {
+            let r = rng.r#gen::<f64>();
+            let idx = cdf.partition_point(|&c| c <= r).min(last);
+            let bits = format!("{:0>width$b}", idx, width = self.n);
+            *counts.entry(bits).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Probability |amp|^2 of each computational basis state.
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.v.iter().map(|amp| amp.norm_sqr()).collect()
+    }
+
+    /// Most probable basis state and its probability.
+    /// Ties resolve to the lowest index.
+    pub fn argmax(&self) -> (usize, f64) {
+        self.v
+            .iter()
+            .map(|amp| amp.norm_sqr())
+            .enumerate()
+            .fold((0, 0.0), |best, (i, p)| if p > best.1 { (i, p) } else { best })
+    }
+
+    /// Shannon entropy (in bits) of the computational-basis measurement
+    /// distribution, -sum_i p_i log2 p_i. Unlike `entanglement_entropy`, this
+    /// is basis dependent: |+> has 1 bit here but is a pure product state.
+    pub fn shannon_entropy(&self) -> f64 {
+        self.probabilities()
+            .into_iter()
+            .filter(|&p| p > 1e-15)
+            .map(|p| -p * p.log2())
+            .sum()
+    }
+
+    /// Marginal probability distribution over `qubits`, summing out the rest.
+    /// Bit `j` of the result index corresponds to qubit `qubits[j]`.
+    pub fn marginal(&self, qubits: &[usize]) -> Vec<f64> {
+        self.check_qubit_set(qubits);
+        let mut probs = vec![0.0; 1 << qubits.len()];
+        for (i, amp) in self.v.iter().enumerate() {
+            probs[gather_bits(i, qubits)] += amp.norm_sqr();
+        }
+        probs
+    }
+
+    /// Probability of measuring |0> on qubit `i`.
+    fn prob0(&self, i: usize) -> f64 {
+        let v = &self.v;
+        sum_over_indices(v.len(), |idx| {
+            if (idx >> i) & 1 == 0 { v[idx].norm_sqr() } else { 0.0 }
+        })
+    }
+
+    /// Collapse `qubit` onto `outcome`, whose probability was `prob`: zero out
+    /// inconsistent amplitudes and rescale the rest by 1/sqrt(prob) in the
+    /// same pass, rather than recomputing the norm afterwards. A branch with
+    /// probability below 1e-10 is too inaccurate to rescale by, so it is
+    /// renormalized from the surviving amplitudes instead.
+    fn collapse(&mut self, qubit: usize, outcome: usize, prob: f64) {
+        let scale = if prob > 1e-10 { 1.0 / prob.sqrt() } else { 1.0 };
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each iteration reads and writes only its own index.
+        for_each_index(self.v.len(), move |idx| unsafe {
+            if (idx >> qubit) & 1 != outcome {
+                ptr.write(idx, ZERO);
+            } else {
+                ptr.write(idx, ptr.read(idx) * scale);
+            }
+        });
+        if prob <= 1e-10 {
+            self.normalize();
+        }
+    }
+
+    /// Measure all qubits at once, collapsing the register to a basis state.
+    /// Returns the integer index of the observed basis state.
+    pub fn measure_all(&mut self, rng: &mut impl Rng) -> usize {
+        let r = rng.r#gen::<f64>();
+        let mut cumulative = 0.0;
+        // If rounding leaves the total just below r, fall back to the last
+        // state that can actually be observed, never a zero amplitude
+        let mut outcome = self
+            .v
+            .iter()
+            .rposition(|amp| amp.norm_sqr() > 0.0)
+            .unwrap_or(self.v.len() - 1);
+        for (idx, amp) in self.v.iter().enumerate() {
+            cumulative += amp.norm_sqr();
+            if r < cumulative {
+                outcome = idx;
+                break;
+            }
+        }
+
+        // Collapse: the state becomes the observed basis vector
+        self.v.fill(ZERO);
+        self.v[outcome] = ONE;
+        outcome
+    }
+
+    // ---- Noise channels (stochastic unraveling) ----
+
+    /// Depolarizing noise: with probability `p` apply one of X, Y, Z
+    /// (each with probability p/3) to `qubit`, otherwise leave it unchanged.
+    /// Averaging over many runs reproduces the depolarizing channel.
+    pub fn depolarize(&mut self, qubit: usize, p: f64, rng: &mut impl Rng) -> &mut Self {
+        assert!((0.0..=1.0).contains(&p), "Probability must be in [0, 1], got {p}");
+        let r = rng.r#gen::<f64>();
+        if r < p / 3.0 {
+            self.apply1q(&X_GATE, qubit);
+        } else if r < 2.0 * p / 3.0 {
+            self.apply1q(&Y_GATE, qubit);
+        } else if r < p {
+            self.apply1q(&Z_GATE, qubit);
+        }
+        self
+    }
+
+    /// Bit-flip noise: apply X to `qubit` with probability `p`.
+    pub fn bit_flip(&mut self, qubit: usize, p: f64, rng: &mut impl Rng) -> &mut Self {
+        assert!((0.0..=1.0).contains(&p), "Probability must be in [0, 1], got {p}");
+        if rng.r#gen::<f64>() < p {
+            self.apply1q(&X_GATE, qubit);
+        }
+        self
+    }
+
+    /// Phase-flip noise: apply Z to `qubit` with probability `p`.
+    pub fn phase_flip(&mut self, qubit: usize, p: f64, rng: &mut impl Rng) -> &mut Self {
+        assert!((0.0..=1.0).contains(&p), "Probability must be in [0, 1], got {p}");
+        if rng.r#gen::<f64>() < p {
+            self.apply1q(&Z_GATE, qubit);
+        }
+        self
+    }
+
+    /// Amplitude-damping (T1) noise by quantum-jump unraveling. With
+    /// probability gamma * P(qubit = 1) the jump |0><1| is applied (the
+    /// qubit decays to |0>); otherwise the no-jump Kraus operator
+    /// diag(1, sqrt(1 - gamma)) shrinks the |1> component. The state is
+    /// renormalized either way.
+    pub fn amplitude_damp(&mut self, qubit: usize, gamma: f64, rng: &mut impl Rng) -> &mut Self {
+        assert!(qubit < self.n, "Invalid qubit {qubit}. Must be in [0, {})", self.n);
+        assert!((0.0..=1.0).contains(&gamma), "Probability must be in [0, 1], got {gamma}");
+        let p_jump = gamma * (1.0 - self.prob0(qubit));
+        let jump = rng.r#gen::<f64>() < p_jump;
+        let keep1 = (1.0 - gamma).sqrt();
+        let len = self.v.len();
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each (i, j) pair differs only in the qubit bit and is
+        // visited once, from the index with the bit clear.
+        for_each_index(len, move |i| {
+            let j = conjugate_index(i, qubit);
+            if i > j {
+                return;
+            }
+            unsafe {
+                if jump {
+                    ptr.write(i, ptr.read(j));
+                    ptr.write(j, ZERO);
+                } else {
+                    ptr.write(j, ptr.read(j) * keep1);
+                }
+            }
+        });
+        self.normalize();
+        self
+    }
+
+    // ---- Qubit reordering ----
+
+    /// Return a new state with qubits relabeled: qubit `i` of the result
+    /// comes from qubit `perm[i]` of `self`.
+    pub fn permute(&self, perm: &[usize]) -> QReg {
+        assert!(
+            perm.len() == self.n,
+            "Permutation has length {}, expected {}",
+            perm.len(),
+            self.n
+        );
+        self.check_qubit_set(perm);
+
+        let mut v = Array1::zeros(self.v.len());
+        for (i, &amp) in self.v.iter().enumerate() {
+            v[gather_bits(i, perm)] = amp;
+        }
+        QReg { v, n: self.n }
+    }
+
+    /// Return a new state with the qubit order reversed, so qubit 0 becomes
+    /// the most significant bit. Useful when comparing against tools with the
+    /// opposite endianness convention.
+    pub fn reverse_qubits(&self) -> QReg {
+        let perm: Vec<usize> = (0..self.n).rev().collect();
+        self.permute(&perm)
+    }
+
+    /// Append `k` ancilla qubits in |0>, returning `self ⊗ |0...0>`.
+    ///
+    /// Same as `self * zeros(k)`: the ancillas become qubits 0..k and the
+    /// original qubits move up to k..n+k, so `ket("1").extend(2)` is |100>.
+    pub fn extend(&self, k: usize) -> QReg {
+        let mut v = Array1::zeros(self.v.len() << k);
+        for (i, &amp) in self.v.iter().enumerate() {
+            v[i << k] = amp;
+        }
+        QReg { v, n: self.n + k }
+    }
+
+    // ---- Subsystems and entanglement ----
+
+    /// Assert that `qubits` are valid and distinct qubit indices.
+    fn check_qubit_set(&self, qubits: &[usize]) {
+        for (j, &q) in qubits.iter().enumerate() {
+            assert!(q < self.n, "Invalid qubit {q}. Must be in [0, {})", self.n);
+            assert!(!qubits[..j].contains(&q), "Duplicate qubit {q}");
+        }
+    }
+
+    /// Density matrix rho = |psi><psi| of the full state.
+    ///
+    /// This is a dense 2^n x 2^n matrix: 16 * 4^n bytes, so 1 GiB already at
+    /// 13 qubits. Use `reduced_density_matrix` for subsystems of large states.
+    pub fn density_matrix(&self) -> Array2<Complex64> {
+        let len = self.v.len();
+        Array2::from_shape_fn((len, len), |(i, j)| self.v[i] * self.v[j].conj())
+    }
+
+    /// Reduced density matrix of the qubits in `keep`, tracing out the rest.
+    ///
+    /// Returns a 2^k x 2^k matrix where bit `j` of the row/column index
+    /// corresponds to qubit `keep[j]`.
+    pub fn reduced_density_matrix(&self, keep: &[usize]) -> Array2<Complex64> {
+        self.check_qubit_set(keep);
+        let traced: Vec<usize> = (0..self.n).filter(|q| !keep.contains(q)).collect();
+
+        // Reshape the state as psi[env, sys], then rho = psi^T . conj(psi)
+        let mut psi = Array2::zeros((1 << traced.len(), 1 << keep.len()));
+        for (i, &amp) in self.v.iter().enumerate() {
+            psi[[gather_bits(i, &traced), gather_bits(i, keep)]] = amp;
+        }
+        psi.t().dot(&psi.mapv(|x| x.conj()))
+    }
+
+    /// Bloch vector (<X>, <Y>, <Z>) of a single qubit, from its reduced
+    /// density matrix. Entangled qubits have vectors inside the unit sphere.
+    pub fn bloch_vector(&self, qubit: usize) -> (f64, f64, f64) {
+        let rho = self.reduced_density_matrix(&[qubit]);
+        // rho = (I + x X + y Y + z Z) / 2
+        let x = 2.0 * rho[[0, 1]].re;
+        let y = -2.0 * rho[[0, 1]].im;
+        let z = (rho[[0, 0]] - rho[[1, 1]]).re;
+        (x, y, z)
+    }
+
+    /// Von Neumann entanglement entropy (in bits) between the qubits in
+    /// `partition` and the rest of the register.
+    pub fn entanglement_entropy(&self, partition: &[usize]) -> f64 {
+        let rho = self.reduced_density_matrix(partition);
+        hermitian_eigenvalues(&rho)
+            .into_iter()
+            .filter(|&lambda| lambda > 1e-12)
+            .map(|lambda| -lambda * lambda.log2())
+            .sum()
+    }
+
+    /// Purity Tr(rho^2) of the reduced state of the qubits in `keep`:
+    /// 1 for a pure subsystem, down to 1/2^k when maximally mixed.
+    pub fn purity(&self, keep: &[usize]) -> f64 {
+        // rho is Hermitian, so Tr(rho^2) = sum_ij |rho_ij|^2
+        self.reduced_density_matrix(keep)
+            .iter()
+            .map(|x| x.norm_sqr())
+            .sum()
+    }
+
+    /// Whether the state factorizes across `partition` and the remaining
+    /// qubits, i.e. the reduced state of `partition` is pure: its purity is
+    /// within `tol` of 1.
+    pub fn is_product(&self, partition: &[usize], tol: f64) -> bool {
+        (1.0 - self.purity(partition)).abs() < tol
+    }
+
+    /// Wootters concurrence |<psi~|psi>| of a two-qubit pure state, where
+    /// |psi~> = (Y ⊗ Y)|psi*>: 0 for product states, 1 for Bell states.
+    pub fn concurrence(&self) -> f64 {
+        assert!(self.n == 2, "Concurrence requires exactly 2 qubits, got {}", self.n);
+        // Y ⊗ Y maps (a, b, c, d) to (-d, c, b, -a), so the overlap
+        // reduces to 2(psi00 psi11 - psi01 psi10)
+        let v = &self.v;
+        (2.0 * (v[0] * v[3] - v[1] * v[2])).norm()
+    }
+}
+
+impl fmt::Display for QReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.terms())
+    }
+}
+
+impl fmt::Debug for QReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QReg")
+            .field("n", &self.n)
+            .field("terms", &self.terms())
+            .finish()
+    }
+}
+
+/// Read the amplitude of basis state `i`.
+impl Index<usize> for QReg {
+    type Output = Complex64;
+    fn index(&self, i: usize) -> &Complex64 {
+        &self.v[i]
+    }
+}
+
+/// Write the amplitude of basis state `i`. The state is not renormalized;
+/// call `normalize()` after editing amplitudes.
+impl IndexMut<usize> for QReg {
+    fn index_mut(&mut self, i: usize) -> &mut Complex64 {
+        &mut self.v[i]
+    }
+}
+
+/// Equality is approximate: two registers are equal when every amplitude
+/// agrees within the `isclose` tolerance. It is not transitive, and states
+/// differing only by a global phase are not equal.
+impl PartialEq for QReg {
+    fn eq(&self, other: &QReg) -> bool {
+        self.isclose(other)
+    }
+}
+
+impl Add for QReg {
+    type Output = QReg;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: QReg) -> QReg {
+        let inv_sqrt2 = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+        let v = (&self.v + &other.v).mapv(|x| x * inv_sqrt2);
+        QReg::from_array(v)
+    }
+}
+
+impl Sub for QReg {
+    type Output = QReg;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: QReg) -> QReg {
+        let inv_sqrt2 = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+        let v = (&self.v - &other.v).mapv(|x| x * inv_sqrt2);
+        QReg::from_array(v)
+    }
+}
+
+/// Tensor product `a * b` = |a> ⊗ |b>. The left operand becomes the
+/// high-order qubits: if `b` has m qubits, qubits 0..m of the result are
+/// `b`'s and qubits m.. are `a`'s. This matches string concatenation in
+/// `ket`, so `ket(s) * ket(t)` equals `ket(s + t)`.
+impl Mul for QReg {
+    type Output = QReg;
+    /// Tensor product of two quantum states.
+    fn mul(self, other: QReg) -> QReg {
+        QReg::from_array(kron(&self.v, &other.v))
+    }
+}
+
+/// Build a register from a `ket` specification, e.g. `QReg::from("0+")`.
+/// Panics on an invalid specification, like `ket`.
+impl From<&str> for QReg {
+    fn from(vecstring: &str) -> QReg {
+        ket(vecstring)
+    }
+}
+
+impl From<String> for QReg {
+    fn from(vecstring: String) -> QReg {
+        ket(&vecstring)
+    }
+}
+
+// ---- Convenience Functions ----
+
+/// Largest register `ket` will build: 2^32 amplitudes is already 64 GiB,
+/// so longer specifications are rejected rather than attempting the
+/// allocation.
+pub const MAX_KET_QUBITS: usize = 32;
+
+/// Create a quantum ket state from a string specification.
+///
+/// Characters: '0' = |0>, '1' = |1>, '+' = |+>, '-' = |->,
+/// 'i' = |i> = (|0> + i|1>)/sqrt(2), 'j' = |-i> = (|0> - i|1>)/sqrt(2)
+///
+/// A pattern followed by `*N` is repeated N times, so "0*3" is "000" and
+/// "01*3" is "010101". The pattern is everything since the previous repeat.
+///
+/// Examples: "0", "1", "00", "01", "++", "+-", "101", "ij", "0*20", "1+*2"
+///
+/// Panics on an invalid specification; see `try_ket` for the checked version.
+pub fn ket(vecstring: &str) -> QReg {
+    try_ket(vecstring).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Like `ket`, but returns an error for an empty string, an invalid
+/// character, a bad repeat count or more than `MAX_KET_QUBITS` qubits
+/// instead of panicking, so it is safe for parsing user input.
+pub fn try_ket(vecstring: &str) -> Result<QReg, String> {
+    if vecstring.is_empty() {
+        return Err("vecstring cannot be empty".to_string());
+    }
+
+    let expanded = expand_repeats(vecstring)?;
+    let vecstring = expanded.as_str();
+
+    let valid = ['0', '1', '+', '-', 'i', 'j'];
+    if let Some(ch) = vecstring.chars().find(|ch| !valid.contains(ch)) {
+        return Err(format!(
+            "Invalid character '{ch}' in vecstring. Valid: 0, 1, +, -, i, j"
+        ));
+    }
+
+    let qvec = |s: char| -> [Complex64; 2] {
+        match s {
+            '0' => [ONE, ZERO],
+            '1' => [ZERO, ONE],
+            '+' => [S2, S2],
+            '-' => [S2, NEG_S2],
+            'i' => [S2, S2_IM],
+            'j' => [S2, NEG_S2_IM],
+            _ => unreachable!(),
+        }
+    };
+
+    // Build the product state in one allocation: each character, from the
+    // rightmost (qubit 0) up, doubles the filled prefix of the vector.
+    let n = vecstring.chars().count();
+    if n > MAX_KET_QUBITS {
+        return Err(format!(
+            "vecstring has {n} qubits; at most {MAX_KET_QUBITS} are supported"
+        ));
+    }
+    let mut v = Array1::zeros(1usize << n);
+    v[0] = ONE;
+    for (q, ch) in vecstring.chars().rev().enumerate() {
+        let [c0, c1] = qvec(ch);
+        let len = 1usize << q;
+        for idx in 0..len {
+            let a = v[idx];
+            v[idx] = a * c0;
+            v[idx + len] = a * c1;
+        }
+    }
+    Ok(QReg { v, n })
+}
+
+/// Expand run-length repeats in a ket string ("01*2" -> "0101").
+pub(crate) fn expand_repeats(vecstring: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = vecstring;
+    while let Some(star) = rest.find('*') {
+        let pattern = &rest[..star];
+        if pattern.is_empty() {
+            return Err(format!(
+                "Repeat '*' must follow a pattern in vecstring \"{vecstring}\""
+            ));
+        }
+        let after = &rest[star + 1..];
+        let end = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        let count = match after[..end].parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => {
+                let token = after.split('*').next().unwrap_or("");
+                return Err(format!(
+                    "Invalid repeat count '{token}' in vecstring \"{vecstring}\". \
+                     Expected a positive integer after '*'"
+                ));
+            }
+        };
+        let total = pattern
+            .chars()
+            .count()
+            .checked_mul(count)
+            .and_then(|len| len.checked_add(expanded.chars().count()))
+            .filter(|&len| len <= MAX_KET_QUBITS);
+        if total.is_none() {
+            return Err(format!(
+                "vecstring \"{vecstring}\" expands to more than {MAX_KET_QUBITS} qubits"
+            ));
+        }
+        expanded.push_str(&pattern.repeat(count));
+        rest = &after[end..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Create the product state of single-qubit states given as 2-vectors
+/// [alpha, beta]. `specs[k]` is the state of qubit k, so the order is the
+/// reverse of a ket string: `product(&[zero, i])` is `ket("i0")`. The
+/// result is normalized, so the specs need not be.
+pub fn product(specs: &[Array1<Complex64>]) -> QReg {
+    assert!(!specs.is_empty(), "product requires at least one qubit");
+    let n = specs.len();
+    let mut v = Array1::zeros(1usize << n);
+    v[0] = ONE;
+    for (q, spec) in specs.iter().enumerate() {
+        assert!(
+            spec.len() == 2,
+            "Qubit {q} state must have 2 amplitudes, got {}",
+            spec.len()
+        );
+        let len = 1usize << q;
+        for idx in 0..len {
+            let a = v[idx];
+            v[idx] = a * spec[0];
+            v[idx + len] = a * spec[1];
+        }
+    }
+    QReg::from_array(v)
+}
+
+/// Create the computational basis state |index> on `n` qubits.
+///
+/// Bit 0 of `index` is qubit 0 (the rightmost character in the display),
+/// so `basis(3, 5)` is |101>.
+pub fn basis(n: usize, index: usize) -> QReg {
+    assert!(n < usize::BITS as usize, "Too many qubits for a usize index: {n}");
+    let len = 1usize << n;
+    assert!(index < len, "Basis index {index} out of range for {n} qubits");
+    let mut v = Array1::zeros(len);
+    v[index] = ONE;
+    QReg { v, n }
+}
+
+/// Create the all-zeros state |0...0> on `n` qubits.
+///
+/// Equivalent to `ket(&"0".repeat(n))` without the per-character kron loop.
+pub fn zeros(n: usize) -> QReg {
+    basis(n, 0)
+}
+
+/// Create the uniform superposition of all 2^n basis states, each with
+/// amplitude 1/sqrt(2^n). Equivalent to `zeros(n).h_all()` in one pass.
+pub fn uniform(n: usize) -> QReg {
+    assert!(n < usize::BITS as usize, "Too many qubits for a usize index: {n}");
+    let len = 1usize << n;
+    let amp = Complex64::new(1.0 / (len as f64).sqrt(), 0.0);
+    QReg { v: Array1::from_elem(len, amp), n }
+}
+
+/// Create the n-qubit GHZ state (|0...0> + |1...1>)/sqrt(2), built with
+/// H on qubit 0 followed by a CNOT chain.
+pub fn ghz(n: usize) -> QReg {
+    assert!(n > 0, "GHZ state requires at least one qubit");
+    let mut q = zeros(n).h(0);
+    for i in 0..n - 1 {
+        q.cnot_mut(i, i + 1);
+    }
+    q
+}
+
+/// Create the Bell state (|00> + |11>)/sqrt(2).
+pub fn bell() -> QReg {
+    ghz(2)
+}
+
+/// Create the Dicke state |D(n, k)>, the equal superposition of all basis
+/// states with exactly `k` ones, normalized by sqrt(n choose k). `dicke(n, 1)`
+/// is the W state; `dicke(n, 0)` and `dicke(n, n)` are |0...0> and |1...1>.
+pub fn dicke(n: usize, k: usize) -> QReg {
+    assert!(k <= n, "Excitation number {k} exceeds qubit count {n}");
+    assert!(n < usize::BITS as usize, "Too many qubits for a usize index: {n}");
+    let len = 1usize << n;
+    let count = (0..len).filter(|i| i.count_ones() as usize == k).count();
+    let amp = Complex64::new(1.0 / (count as f64).sqrt(), 0.0);
+    let v = (0..len)
+        .map(|i| if i.count_ones() as usize == k { amp } else { ZERO })
+        .collect();
+    QReg { v, n }
+}
+
+/// Create the n-qubit W state (|10...0> + |010...0> + ... + |0...01>)/sqrt(n),
+/// the equal superposition of all single-excitation basis states.
+pub fn w_state(n: usize) -> QReg {
+    assert!(n > 0, "W state requires at least one qubit");
+    assert!(n < usize::BITS as usize, "Too many qubits for a usize index: {n}");
+    let amp = Complex64::new(1.0 / (n as f64).sqrt(), 0.0);
+    let mut v = Array1::zeros(1usize << n);
+    for k in 0..n {
+        v[1 << k] = amp;
+    }
+    QReg { v, n }
+}
+
+/// Draw a standard normal variate using the Box-Muller transform.
+fn gaussian(rng: &mut impl Rng) -> f64 {
+    // 1 - u keeps the argument of ln in (0, 1]
+    let u1 = 1.0 - rng.r#gen::<f64>();
+    let u2 = rng.r#gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Create a Haar-random pure state on `n` qubits.
+///
+/// Amplitudes have independent Gaussian real and imaginary parts and the
+/// result is normalized, which is uniform over the unit sphere.
+pub fn random(n: usize, rng: &mut impl Rng) -> QReg {
+    assert!(n < usize::BITS as usize, "Too many qubits for a usize index: {n}");
+    let v = (0..1usize << n)
+        .map(|_| Complex64::new(gaussian(rng), gaussian(rng)))
+        .collect();
+    QReg::from_array(v)
+}
+
+/// Create the weighted superposition sum_i c_i |psi_i>, normalized.
+///
+/// Unlike `+`, which always uses equal 1/sqrt(2) weights, the coefficients
+/// here are arbitrary. All states must have the same number of qubits.
+///
+/// Panics on invalid input; see `try_superpose` for the checked version.
+pub fn superpose(states: &[(Complex64, QReg)]) -> QReg {
+    try_superpose(states).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Like `superpose`, but returns an error for an empty list, mismatched
+/// lengths or terms that cancel to the zero vector instead of panicking.
+pub fn try_superpose(states: &[(Complex64, QReg)]) -> Result<QReg, String> {
+    let Some((_, first)) = states.first() else {
+        return Err("superpose requires at least one state".to_string());
+    };
+    let len = first.v.len();
+    let mut v = Array1::zeros(len);
+    for (c, q) in states {
+        if q.v.len() != len {
+            return Err(format!(
+                "All states must have the same length, got {} and {len}",
+                q.v.len()
+            ));
+        }
+        v.scaled_add(*c, &q.v);
+    }
+    QReg::try_new(v.to_vec())
+}
+
+// ---- Circuits ----
+
+pub mod circuit;
+pub use circuit::{from_qasm, Circuit, Gate};
+#[cfg(feature = "serde")]
+pub use circuit::circuit_from_json;
+
+pub mod classical;
+pub use classical::ClassicalReg;
+
+pub mod qreg32;
+pub use qreg32::QReg32;
+
+pub mod sparse;
+pub use sparse::SparseQReg;
+
+// ---- Python Bindings ----
+
+#[cfg(feature = "pyo3")]
+mod python;
+
+// ---- Tests ----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // -- Utility tests --
+
+    #[test]
+    fn test_nqubits() {
+        assert_eq!(nqubits(2), 1);
+        assert_eq!(nqubits(4), 2);
+        assert_eq!(nqubits(16), 4);
+    }
+
+    #[test]
+    fn test_nqubits_checked() {
+        assert_eq!(nqubits_checked(1), Some(0));
+        assert_eq!(nqubits_checked(2), Some(1));
+        assert_eq!(nqubits_checked(4), Some(2));
+        assert_eq!(nqubits_checked(8), Some(3));
+        assert_eq!(nqubits_checked(3), None);
+        assert_eq!(nqubits_checked(6), None);
+        assert_eq!(nqubits_checked(12), None);
+    }
+
+    #[test]
+    fn test_conjugate_index() {
+        assert_eq!(conjugate_index(0, 0), 1); // |0> -> |1>
+        assert_eq!(conjugate_index(1, 0), 0); // |1> -> |0>
+        assert_eq!(conjugate_index(2, 1), 0); // |10> -> |00>
+    }
+
+    #[test]
+    fn test_is_unitary() {
+        for m in [&*I_GATE, &*X_GATE, &*Y_GATE, &*H_GATE, &*T_GATE, &*CNOT_GATE, &*CPHASE_GATE] {
+            assert!(is_unitary(m, 1e-12));
+        }
+        assert!(is_unitary(&rx_gate(0.4).dot(&ry_gate(1.1)), 1e-12));
+        assert!(!is_unitary(&H_GATE.mapv(|x| x * 1.1), 1e-8));
+        assert!(!is_unitary(&array![[ONE, ONE], [ZERO, ONE]], 1e-8));
+        assert!(!is_unitary(&Array2::from_elem((2, 3), ZERO), 1e-8));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not unitary")]
+    fn test_apply_non_unitary_debug() {
+        ket("0").apply1q(&X_GATE.mapv(|x| x * 2.0), 0);
+    }
+
+    // -- Checked construction --
+
+    #[test]
+    fn test_try_new() {
+        let q = QReg::try_new(vec![ONE, ONE]).unwrap();
+        assert!(q.isclose(&ket("+")));
+        assert_eq!(QReg::try_new(vec![]).unwrap_err(), "Register cannot be empty");
+        assert_eq!(
+            QReg::try_new(vec![ONE, ZERO, ZERO]).unwrap_err(),
+            "Register length must be power of 2, got 3"
+        );
+        assert_eq!(
+            QReg::try_new(vec![ZERO; 4]).unwrap_err(),
+            "Cannot normalize zero vector"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Register length must be power of 2, got 6")]
+    fn test_new_panics() {
+        QReg::new(vec![ONE; 6]);
+    }
+
+    #[test]
+    fn test_from_array_raw_keeps_norm() {
+        let v = array![Complex64::new(3.0, 0.0), Complex64::new(0.0, 4.0)];
+        let mut q = QReg::from_array_raw(v.clone());
+        assert_eq!(q.n, 1);
+        assert_eq!(q.v, v);
+        assert!((q.norm() - 5.0).abs() < 1e-12);
+        q.normalize();
+        assert!((q.v[1] - Complex64::new(0.0, 0.8)).norm() < 1e-12);
+    }
+
+    #[test]
+    #[cfg(feature = "npy")]
+    fn test_npy_roundtrip() {
+        let path = std::env::temp_dir().join(format!("rvecsim_bell_{}.npy", std::process::id()));
+        let path = path.to_str().unwrap();
+        let bell = ket("00").h(0).cnot(0, 1).s(1);
+        bell.save_npy(path).unwrap();
+        let loaded = QReg::load_npy(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.n, 2);
+        assert_eq!(loaded.v, bell.v);
+        assert!(QReg::load_npy(path).is_err());
+    }
+
+    // -- Ket construction tests --
+
+    #[test]
+    fn test_ket_single_qubits() {
+        assert_eq!(ket("0").to_string(), "1.0|0>");
+        assert_eq!(ket("1").to_string(), "1.0|1>");
+    }
+
+    #[test]
+    fn test_ket_two_qubits() {
+        assert_eq!(ket("00").to_string(), "1.0|00>");
+        assert_eq!(ket("10").to_string(), "1.0|10>");
+        assert_eq!(ket("11").to_string(), "1.0|11>");
+    }
+
+    #[test]
+    fn test_ket_three_qubits() {
+        assert_eq!(ket("101").to_string(), "1.0|101>");
+    }
+
+    #[test]
+    fn test_ket_matches_kron_construction() {
+        // Reference: the original kron-per-character construction
+        let kron_ket = |s: &str| {
+            let mut register = Array1::from_vec(vec![ONE]);
+            for ch in s.chars().rev() {
+                let single = ket(&ch.to_string()).v;
+                register = kron(&single, &register);
+            }
+            QReg::from_array(register)
+        };
+        for s in ["0", "1", "01", "10", "+-", "-+0", "i1j", "10+-ij", "0110+"] {
+            assert!(ket(s).isclose(&kron_ket(s)), "ket({s:?})");
+        }
+    }
+
+    #[test]
+    fn test_ket_repeat_syntax() {
+        assert!(ket("0*3").isclose(&ket("000")));
+        assert!(ket("01*3").isclose(&ket("010101")));
+        assert!(ket("1+*2-").isclose(&ket("1+1+-")));
+        assert_eq!(ket("0*12").n, 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid repeat count 'abc'")]
+    fn test_ket_repeat_bad_count() {
+        ket("0*abc");
+    }
+
+    #[test]
+    fn test_try_ket() {
+        assert!(try_ket("0+").unwrap().isclose(&ket("0+")));
+        assert_eq!(try_ket("").unwrap_err(), "vecstring cannot be empty");
+        assert_eq!(
+            try_ket("01x").unwrap_err(),
+            "Invalid character 'x' in vecstring. Valid: 0, 1, +, -, i, j"
+        );
+        assert!(try_ket("0*abc").unwrap_err().contains("Invalid repeat count 'abc'"));
+    }
+
+    #[test]
+    fn test_ket_repeat_too_many_qubits() {
+        for s in ["0*64", "0*40", "01*17", "0*20+*20", "0*99999999999999999999999"] {
+            let err = try_ket(s).unwrap_err();
+            assert!(
+                err.contains("more than 32 qubits") || err.contains("Invalid repeat count"),
+                "{s}: {err}"
+            );
+        }
+        assert_eq!(
+            try_ket("0*64").unwrap_err(),
+            "vecstring \"0*64\" expands to more than 32 qubits"
+        );
+        assert!(try_ket("0*3").is_ok());
+        // Written out in full rather than with a repeat
+        assert_eq!(
+            try_ket(&"0".repeat(64)).unwrap_err(),
+            "vecstring has 64 qubits; at most 32 are supported"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 32 are supported")]
+    fn test_ket_too_many_qubits_panics() {
+        ket(&"+".repeat(40));
+    }
+
+    #[test]
+    fn test_ket_y_eigenstates() {
+        let q = ket("i");
+        assert!((q.v[0] - S2).norm() < 1e-10);
+        assert!((q.v[1] - S2_IM).norm() < 1e-10);
+        let q = ket("j");
+        assert!((q.v[1] - NEG_S2_IM).norm() < 1e-10);
+        // |i> and |-i> are eigenstates of Y with eigenvalues +1 and -1
+        assert!(ket("i").y(0).isclose(&ket("i")));
+        let q = ket("j").y(0);
+        assert!((q.v[0] + S2).norm() < 1e-10);
+        assert!((q.v[1] - S2_IM).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_product() {
+        let zero = array![ONE, ZERO];
+        let i = array![S2, S2_IM];
+        assert!(product(&[zero.clone(), i.clone()]).isclose(&ket("i0")));
+        assert!(product(&[i, zero]).isclose(&ket("0i")));
+        // Arbitrary, unnormalized single-qubit states
+        let a = array![Complex64::new(3.0, 0.0), Complex64::new(0.0, 4.0)];
+        let q = product(&[a.clone(), array![ONE, ONE]]);
+        assert!(q.isclose(&(ket("+") * QReg::from_array(a))));
+    }
+
+    #[test]
+    fn test_basis() {
+        assert_eq!(basis(3, 5).to_string(), "1.0|101>");
+        assert!(basis(2, 1).isclose(&ket("01")));
+        assert!(basis(1, 0).isclose(&ket("0")));
+    }
+
+    #[test]
+    fn test_zeros() {
+        assert!(zeros(5).isclose(&ket("00000")));
+        assert_eq!(zeros(2).to_string(), "1.0|00>");
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many qubits")]
+    fn test_zeros_too_many_qubits_panics() {
+        zeros(64);
+    }
+
+    #[test]
+    fn test_uniform() {
+        assert!(uniform(3).isclose(&zeros(3).h_all()));
+        assert!(uniform(1).isclose(&ket("+")));
+        assert_eq!(uniform(6).support_size(1e-8), 64);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many qubits")]
+    fn test_uniform_too_many_qubits_panics() {
+        uniform(64);
+    }
+
+    #[test]
+    fn test_w_state() {
+        let w = w_state(3);
+        let amp = 1.0 / 3f64.sqrt();
+        for i in 0..8usize {
+            let expected = if i.count_ones() == 1 { amp } else { 0.0 };
+            assert!((w.v[i] - expected).norm() < 1e-12, "index {i:03b}");
+        }
+        assert!(w_state(1).isclose(&ket("1")));
+        assert!(w_state(2).isclose(&(ket("01") + ket("10"))));
+    }
+
+    #[test]
+    fn test_dicke() {
+        for n in 1..=4 {
+            assert!(dicke(n, 0).isclose(&zeros(n)));
+            assert!(dicke(n, n).isclose(&basis(n, (1 << n) - 1)));
+        }
+        assert!(dicke(3, 1).isclose(&w_state(3)));
+        // D(4, 2): six basis states with two ones, each 1/sqrt(6)
+        let d = dicke(4, 2);
+        assert_eq!(d.support_size(1e-8), 6);
+        assert!((d.v[0b0101] - 1.0 / 6f64.sqrt()).norm() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds qubit count")]
+    fn test_dicke_too_many_excitations() {
+        dicke(2, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many qubits")]
+    fn test_dicke_too_many_qubits_panics() {
+        dicke(64, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many qubits")]
+    fn test_w_state_too_many_qubits_panics() {
+        w_state(64);
+    }
+
+    #[test]
+    fn test_random_state() {
+        let a = random(3, &mut StdRng::seed_from_u64(1));
+        let b = random(3, &mut StdRng::seed_from_u64(2));
+        assert_eq!(a.n, 3);
+        assert!((a.norm() - 1.0).abs() < 1e-10);
+        assert!((b.norm() - 1.0).abs() < 1e-10);
+        assert!(!a.isclose(&b));
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many qubits")]
+    fn test_random_too_many_qubits_panics() {
+        random(64, &mut StdRng::seed_from_u64(1));
+    }
+
+    #[test]
+    fn test_amplitude_lookup() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert!((bell.amplitude("11") - S2).norm() < 1e-10);
+        assert!(bell.amplitude("01").norm() < 1e-10);
+        // Rightmost character is qubit 0
+        assert!((ket("01").amplitude("01") - ONE).norm() < 1e-10);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 2")]
+    fn test_amplitude_wrong_length() {
+        ket("00").amplitude("0");
+    }
+
+    #[test]
+    fn test_prob_bitstring() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert!((bell.prob("11") - 0.5).abs() < 1e-10);
+        assert!(bell.prob("01").abs() < 1e-10);
+        assert!((ket("1+0").prob("110") - 0.5).abs() < 1e-10);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 3")]
+    fn test_prob_wrong_length() {
+        ket("000").prob("0110");
+    }
+
+    // -- Single-qubit gate tests --
+
+    #[test]
+    fn test_x_gate() {
+        assert_eq!(ket("0").x(0).to_string(), "1.0|1>");
+        assert_eq!(ket("1").x(0).to_string(), "1.0|0>");
+        assert_eq!(ket("00").x(0).to_string(), "1.0|01>");
+        assert_eq!(ket("01").x(1).to_string(), "1.0|11>");
+    }
+
+    #[test]
+    fn test_chained_gates() {
+        assert_eq!(ket("00").x(0).x(1).to_string(), "1.0|11>");
+        assert_eq!(ket("+").h(0).x(0).to_string(), "1.0|1>");
+    }
+
+    // -- Two-qubit gate tests --
+
+    #[test]
+    fn test_cnot_gate() {
+        assert_eq!(ket("01").cnot(0, 1).to_string(), "1.0|11>");
+        assert_eq!(ket("00").cnot(0, 1).to_string(), "1.0|00>");
+    }
+
+    // -- Isclose tests --
+
+    #[test]
+    fn test_isclose_plus_plus() {
+        assert!(ket("++").isclose_slice(&[0.5, 0.5, 0.5, 0.5]));
+    }
+
+    #[test]
+    fn test_isclose_minus_minus() {
+        assert!(ket("--").isclose_slice(&[0.5, -0.5, -0.5, 0.5]));
+    }
+
+    // -- Operator tests --
+
+    #[test]
+    fn test_add_gives_plus() {
+        assert!(ket("+").isclose(&(ket("0") + ket("1"))));
+    }
+
+    #[test]
+    fn test_sub_gives_minus() {
+        assert!(ket("-").isclose(&(ket("0") - ket("1"))));
+    }
+
+    #[test]
+    fn test_tensor_product() {
+        let q00 = ket("0") * ket("0");
+        assert!(q00.isclose(&ket("00")));
+    }
+
+    #[test]
+    fn test_superpose_weighted() {
+        let q = superpose(&[(ONE, ket("0")), (Complex64::new(2.0, 0.0), ket("1"))]);
+        let s5 = 5f64.sqrt();
+        assert!(q.isclose_slice(&[1.0 / s5, 2.0 / s5]));
+    }
+
+    #[test]
+    fn test_try_superpose_errors() {
+        assert!(try_superpose(&[]).is_err());
+        assert!(try_superpose(&[(ONE, ket("0")), (ONE, ket("00"))]).is_err());
+        let err = try_superpose(&[(ONE, ket("0")), (NEG1, ket("0"))]).unwrap_err();
+        assert!(err.contains("zero vector"), "{err}");
+    }
+
+    #[test]
+    fn test_add_weighted() {
+        let q = ket("0").add_weighted(Complex64::new(0.6, 0.0), &ket("1"), Complex64::new(0.8, 0.0));
+        assert!(q.isclose_slice(&[0.6, 0.8]));
+        // Weights are renormalized, and match `+` for equal weights
+        let q = ket("0").add_weighted(ONE, &ket("1"), ONE);
+        assert!(q.isclose(&(ket("0") + ket("1"))));
+    }
+
+    // -- Measurement tests --
+
+    #[test]
+    fn test_measure_zero() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut q = ket("0");
+        assert_eq!(q.measure(0, 1, &mut rng), vec![0]);
+    }
+
+    #[test]
+    fn test_measure_one() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut q = ket("1");
+        assert_eq!(q.measure(0, 1, &mut rng), vec![1]);
+    }
+
+    #[test]
+    fn test_measure_collapse() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut q = ket("+");
+        let r1 = q.measure(0, 1, &mut rng);
+        // After collapse, subsequent measurements are deterministic
+        let r2 = q.measure(0, 3, &mut rng);
+        assert!(r2.iter().all(|&x| x == r1[0]));
+    }
+
+    #[test]
+    fn test_measure_into() {
+        let mut rng = StdRng::seed_from_u64(29);
+        let mut creg = ClassicalReg::new(2);
+        let mut q = ket("1+");
+        assert_eq!(q.measure_into(1, 0, &mut creg, &mut rng), 1);
+        let r = q.measure_into(0, 1, &mut creg, &mut rng);
+        assert_eq!(creg.bits, vec![Some(1), Some(r)]);
+        // The register holds the collapsed outcome
+        assert!(q.isclose(&basis(2, 2 + r)));
+    }
+
+    #[test]
+    fn test_teleportation() {
+        let psi = ket("0").ry(0.7, 0).rz(0.3, 0);
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut creg = ClassicalReg::new(2);
+            // psi on qubit 0, Bell pair on qubits 1 and 2
+            let mut q = ket("00") * psi.clone();
+            q.h_mut(1).cnot_mut(1, 2);
+            // Bell measurement of qubits 0 and 1
+            q.cnot_mut(0, 1).h_mut(0);
+            let m0 = q.measure_into(0, 0, &mut creg, &mut rng);
+            let m1 = q.measure_into(1, 1, &mut creg, &mut rng);
+            // Corrections on qubit 2
+            q.x_if(2, 1, &creg, 1).z_if(2, 1, &creg, 0);
+            assert!(q.isclose(&(psi.clone() * basis(2, 2 * m1 + m0))), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn test_x_if() {
+        let mut creg = ClassicalReg::new(1);
+        creg.set(0, 0);
+        let mut q = ket("0");
+        assert!(q.x_if(0, 1, &creg, 0).isclose(&ket("0")));
+        assert!(q.x_if(0, 0, &creg, 0).isclose(&ket("1")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Classical bit 0 has not been measured")]
+    fn test_x_if_unmeasured() {
+        ket("0").x_if(0, 1, &ClassicalReg::new(1), 0);
+    }
+
+    #[test]
+    fn test_measure_basis() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let mut q = ket("+");
+        assert_eq!(q.measure_basis(0, 'x', 20, &mut rng), vec![0; 20]);
+        assert!(q.isclose(&ket("+")));
+        let mut q = ket("j0");
+        assert_eq!(q.measure_basis(1, 'y', 20, &mut rng), vec![1; 20]);
+        assert!(q.isclose(&ket("j0")));
+        // Measuring |0> in X collapses onto |+> or |->
+        let mut q = ket("0");
+        let r = q.measure_basis(0, 'x', 1, &mut rng)[0];
+        assert!(q.isclose(&ket(if r == 0 { "+" } else { "-" })));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid measurement basis 'q'")]
+    fn test_measure_basis_invalid() {
+        ket("0").measure_basis(0, 'q', 1, &mut StdRng::seed_from_u64(0));
+    }
+
+    #[test]
+    fn test_measure_repeated_keeps_norm() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut q = random(5, &mut rng);
+        for i in 0..5 {
+            let shots = q.measure(i, 10, &mut rng);
+            assert!(shots.iter().all(|&x| x == shots[0]));
+            assert!((q.norm() - 1.0).abs() < 1e-12);
+        }
+        // Every qubit is now definite, so the state is a single basis vector
+        let (_, p) = q.argmax();
+        assert!((p - 1.0).abs() < 1e-12);
+    }
+
+    // -- ket terms() direct call (Python: ket('10').terms() == '1.0|10>') --
+
+    #[test]
+    fn test_terms_direct() {
+        assert_eq!(ket("10").terms(), "1.0|10>");
+    }
+
+    // -- Filtered term display --
+
+    #[test]
+    fn test_terms_top() {
+        let q = ket("++++");
+        assert_eq!(q.terms_top(3).split(' ').count(), 3);
+        assert_eq!(q.terms_top(3), "0.25|0000> 0.25|0001> 0.25|0010>");
+        assert_eq!(q.terms_top(100).split(' ').count(), 16);
+        // Largest magnitude first
+        let q = QReg::new(vec![
+            Complex64::new(1.0, 0.0),
+            Complex64::new(3.0, 0.0),
+            Complex64::new(2.0, 0.0),
+            ZERO,
+        ]);
+        assert!(q.terms_top(2).ends_with("|01> 0.534522483824849|10>"));
+    }
+
+    #[test]
+    fn test_terms_with_tol() {
+        let q = QReg::new(vec![Complex64::new(1.0, 0.0), Complex64::new(1e-3, 0.0)]);
+        assert_eq!(q.terms_with_tol(1e-2).split(' ').count(), 1);
+        assert_eq!(q.terms_with_tol(1e-4).split(' ').count(), 2);
+        assert_eq!(q.terms_with_tol(1e-8), q.terms());
+    }
+
+    #[test]
+    fn test_terms_polar() {
+        assert_eq!(ket("1").s(0).terms_polar(), "1.0·e^{iπ/2}|1>");
+        assert_eq!(ket("0").terms_polar(), "1.0|0>");
+        assert_eq!(ket("1").z(0).terms_polar(), "1.0·e^{iπ}|1>");
+        assert_eq!(
+            ket("+").tdg(0).terms_polar(),
+            "0.707106781186548|0> 0.707106781186548·e^{-iπ/4}|1>"
+        );
+        assert_eq!(ket("1").rz(1.0, 0).terms_polar(), "1.0·e^{i0.5}|1>");
+        assert_eq!(ket("0").rz(1.0, 0).terms_polar(), "1.0·e^{-i0.5}|0>");
+    }
+
+    #[test]
+    fn test_terms_width() {
+        let q = ket("1+");
+        assert_eq!(q.terms_width(2), q.terms());
+        assert_eq!(q.terms_width(4), "0.707106781186548|0010> 0.707106781186548|0011>");
+        assert_eq!(q.terms_width(1), "0.707106781186548|0> 0.707106781186548|1>");
+    }
+
+    #[test]
+    fn test_to_terms_vec() {
+        let terms = ket("00").h(0).cnot(0, 1).to_terms_vec(1e-8);
+        assert_eq!(terms.len(), 2);
+        assert_eq!(terms[0].0, "00");
+        assert_eq!(terms[1].0, "11");
+        assert!(terms.iter().all(|(_, a)| (a - S2).norm() < 1e-10));
+        assert_eq!(ket("0+").to_terms_vec(0.8), vec![]);
+        assert_eq!(ket("001").to_terms_vec(1e-8), vec![("001".to_string(), ONE)]);
+    }
+
+    #[test]
+    fn test_support_size() {
+        assert_eq!(ket("00").h(0).cnot(0, 1).support_size(1e-8), 2);
+        for n in 1..=6 {
+            assert_eq!(zeros(n).h_all().support_size(1e-8), 1 << n);
+        }
+        assert_eq!(ket("1011").support_size(1e-8), 1);
+    }
+
+    // -- LaTeX display --
+
+    #[test]
+    fn test_to_latex() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert_eq!(bell.to_latex(), r"\frac{1}{\sqrt{2}}(|00\rangle + |11\rangle)");
+        assert_eq!(ket("-").to_latex(), r"\frac{1}{\sqrt{2}}(|0\rangle - |1\rangle)");
+        assert_eq!(ket("i").to_latex(), r"\frac{1}{\sqrt{2}}(|0\rangle + i|1\rangle)");
+        assert_eq!(
+            ket("++").to_latex(),
+            r"\frac{1}{2}(|00\rangle + |01\rangle + |10\rangle + |11\rangle)"
+        );
+        assert_eq!(ket("1").z(0).to_latex(), r"-|1\rangle");
+        let q = superpose(&[(ONE, ket("0")), (Complex64::new(2.0, 0.0), ket("1"))]);
+        assert_eq!(q.to_latex(), r"\frac{1}{\sqrt{5}}|0\rangle + 0.894427190999916|1\rangle");
+    }
+
+    // -- Global phase --
+
+    #[test]
+    fn test_global_phase() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        assert!((ket("1").z(0).global_phase().abs() - PI).abs() < 1e-12);
+        assert_eq!(ket("0+").global_phase(), 0.0);
+        // Y|1> = -i|0>
+        assert!((ket("1").y(0).global_phase() + FRAC_PI_2).abs() < 1e-12);
+        // RZ(theta)|+> has phase -theta/2 on its first term
+        assert!((ket("+").rz(0.8, 0).global_phase() + 0.4).abs() < 1e-12);
+        let mut q = ket("+").rz(0.8, 0);
+        assert!(q.canonicalize_phase().global_phase().abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_canonicalize_phase() {
+        let mut q = ket("1").z(0);
+        assert_eq!(q.to_string(), "-1.0|1>");
+        q.canonicalize_phase();
+        assert_eq!(q.to_string(), "1.0|1>");
+
+        // Y|1> = -i|0> canonicalizes to |0>
+        let mut q = ket("1").y(0);
+        assert!(q.canonicalize_phase().isclose(&ket("0")));
+
+        // Relative phases are preserved: Y|+> = -i|-> canonicalizes to |->
+        let mut q = ket("+").y(0);
+        q.canonicalize_phase();
+        assert!(q.v[0].im.abs() < 1e-10 && q.v[0].re > 0.0);
+        assert!((q.v[1] / q.v[0] + ONE).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_isclose_up_to_phase() {
+        let minus_one = ket("1").z(0);
+        assert!(minus_one.isclose_up_to_phase(&ket("1")));
+        assert!(!minus_one.isclose(&ket("1")));
+        // Relative phase still matters
+        assert!(!ket("+").isclose_up_to_phase(&ket("-")));
+        assert!(ket("+").y(0).isclose_up_to_phase(&ket("-")));
+        assert!(!ket("0").isclose_up_to_phase(&ket("00")));
+    }
+
+    #[test]
+    fn test_inner_product() {
+        assert!((ket("+").inner(&ket("0")) - S2).norm() < 1e-10);
+        assert!(ket("+").inner(&ket("-")).norm() < 1e-10);
+        // <0|i> is 1/sqrt(2), <i|1> is -i/sqrt(2)
+        assert!((ket("i").inner(&ket("1")) - NEG_S2_IM).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_conjugate() {
+        assert!(ket("i").conjugate().isclose(&ket("j")));
+        assert!(ket("+1").conjugate().isclose(&ket("+1")));
+        let q = random(3, &mut StdRng::seed_from_u64(71));
+        assert!(q.conjugate().conjugate().isclose(&q));
+        // <a*|b*> = <a|b>*
+        let p = random(3, &mut StdRng::seed_from_u64(72));
+        assert!((q.conjugate().inner(&p.conjugate()) - q.inner(&p).conj()).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        let computational: Vec<QReg> = (0..4).map(|i| basis(2, i)).collect();
+        let expected = [S2, ZERO, ZERO, S2];
+        for (o, e) in bell.overlaps(&computational).iter().zip(expected) {
+            assert!((o - e).norm() < 1e-12);
+        }
+        // <b|psi>, not <psi|b>: the phase of |i> shows up unconjugated
+        let o = ket("i").overlaps(&[ket("1")]);
+        assert!((o[0] - S2_IM).norm() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn test_overlaps_length_mismatch() {
+        ket("0").overlaps(&[ket("00")]);
+    }
+
+    #[test]
+    fn test_fidelity_and_trace_distance() {
+        let q = random(3, &mut StdRng::seed_from_u64(61));
+        assert!((q.fidelity(&q) - 1.0).abs() < 1e-12);
+        assert!(q.trace_distance(&q).abs() < 1e-6);
+        assert!(ket("1").z(0).trace_distance(&ket("1")) < 1e-6);
+        assert!((ket("01").trace_distance(&ket("10")) - 1.0).abs() < 1e-12);
+        assert!((ket("+").trace_distance(&ket("i")) - S2.re).abs() < 1e-12);
+        assert!((ket("+").fidelity(&ket("0")) - 0.5).abs() < 1e-12);
+    }
+
+    // -- PartialEq and Debug --
+
+    #[test]
+    fn test_partial_eq() {
+        let a = ket("00").h(0).cnot(0, 1);
+        let b = (ket("00") + ket("11")).cnot(1, 0).cnot(1, 0);
+        assert_eq!(a, b);
+        assert_ne!(a, ket("00"));
+        assert_ne!(ket("1").z(0), ket("1"));
+    }
+
+    #[test]
+    fn test_debug() {
+        let s = format!("{:?}", ket("01"));
+        assert!(!s.is_empty());
+        assert_eq!(s, r#"QReg { n: 2, terms: "1.0|01>" }"#);
+    }
+
+    // -- Indexing --
+
+    #[test]
+    fn test_index_amplitudes() {
+        let mut q = ket("00").h(0).cnot(0, 1);
+        assert!((q[3] - S2).norm() < 1e-10);
+        assert!(q[1].norm() < 1e-10);
+
+        q[3] = ZERO;
+        q[1] = ONE;
+        q.normalize();
+        assert!(q.isclose_slice(&[
+            std::f64::consts::FRAC_1_SQRT_2 / (1.5f64).sqrt(),
+            1.0 / (1.5f64).sqrt(),
+            0.0,
+            0.0
+        ]));
+    }
+
+    // -- ket('01') missing from construction tests --
+
+    #[test]
+    fn test_ket_01() {
+        assert_eq!(ket("01").to_string(), "1.0|01>");
+    }
+
+    // -- Y gate tests --
+
+    #[test]
+    fn test_y_gate() {
+        // Y|0> = i|1>
+        let q = ket("0").y(0);
+        assert!(q.v[0].norm() < 1e-8);
+        assert!((q.v[1] - Complex64::new(0.0, 1.0)).norm() < 1e-5);
+
+        // Y|1> = -i|0>
+        let q = ket("1").y(0);
+        assert!((q.v[0] - Complex64::new(0.0, -1.0)).norm() < 1e-5);
+        assert!(q.v[1].norm() < 1e-8);
+    }
+
+    // -- Z gate tests --
+
+    #[test]
+    fn test_z_gate() {
+        // Z|0> = |0>
+        assert_eq!(ket("0").z(0).to_string(), "1.0|0>");
+        // Z|1> = -|1>
+        assert_eq!(ket("1").z(0).to_string(), "-1.0|1>");
+    }
+
+    // -- H gate tests --
+
+    #[test]
+    fn test_h_gate() {
+        // H|0> = |+>
+        assert!(ket("0").h(0).isclose(&ket("+")));
+        // H|1> = |->
+        assert!(ket("1").h(0).isclose(&ket("-")));
+        // H|+> = |0>
+        assert!(ket("+").h(0).isclose(&ket("0")));
+        // H|-> = |1>
+        assert!(ket("-").h(0).isclose(&ket("1")));
+        // HH = I (self-inverse)
+        assert!(ket("0").h(0).h(0).isclose(&ket("0")));
+        assert!(ket("1").h(0).h(0).isclose(&ket("1")));
+    }
+
+    // -- S gate tests --
+
+    #[test]
+    fn test_s_gate() {
+        // S|0> = |0>
+        assert_eq!(ket("0").s(0).to_string(), "1.0|0>");
+        // S|1> = i|1>
+        let q = ket("1").s(0);
+        assert!(q.v[0].norm() < 1e-8);
+        assert!((q.v[1] - Complex64::new(0.0, 1.0)).norm() < 1e-5);
+    }
+
+    // -- Complex coefficient formatting --
+
+    #[test]
+    fn test_complex_formatting() {
+        let q = QReg::new(vec![
+            Complex64::new(0.5, -0.5),
+            Complex64::new(0.5, 0.5),
+        ]);
+        assert_eq!(q.to_string(), "0.5-0.5i|0> 0.5+0.5i|1>");
+        // Purely imaginary coefficients have no "0.0+" prefix
+        assert_eq!(ket("0").y(0).to_string(), "1.0i|1>");
+        assert_eq!(ket("1").y(0).to_string(), "-1.0i|0>");
+    }
+
+    // -- Sdg, T, Tdg and RZ gate tests --
+
+    #[test]
+    fn test_phase_family_gates() {
+        // S Sdg = I, T T = S, T Tdg = I
+        assert!(ket("+").s(0).sdg(0).isclose(&ket("+")));
+        assert!(ket("+").t(0).t(0).isclose(&ket("+").s(0)));
+        assert!(ket("+").t(0).tdg(0).isclose(&ket("+")));
+        // RZ(pi/2) equals S up to a global phase of e^{-i pi/4}
+        let q = ket("+").rz(std::f64::consts::FRAC_PI_2, 0);
+        let phase = Complex64::from_polar(1.0, -std::f64::consts::FRAC_PI_4);
+        let s = ket("+").s(0);
+        assert!((q.v[0] - phase * s.v[0]).norm() < 1e-10);
+        assert!((q.v[1] - phase * s.v[1]).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_rotation_gates() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        // RX(pi)|0> = -i|1>, RY(pi/2)|0> = |+>
+        assert!((ket("0").rx(PI, 0).v[1] - NEG_IM).norm() < 1e-10);
+        assert!(ket("0").ry(FRAC_PI_2, 0).isclose(&ket("+")));
+        // P(pi/2) = S, and P matches RZ up to a global phase
+        assert!(ket("+").p(FRAC_PI_2, 0).isclose(&ket("+").s(0)));
+        assert!(ket("+").p(1.3, 0).isclose_up_to_phase(&ket("+").rz(1.3, 0)));
+        // The in-place variants compose and stay unitary
+        let mut q = ket("i+");
+        q.rx_mut(0.7, 0).ry_mut(-0.4, 1).p_mut(0.2, 0);
+        assert!((q.norm() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_u1q_dag_undoes_apply1q() {
+        let mut rng = StdRng::seed_from_u64(59);
+        let q = random(3, &mut rng);
+        for target in 0..3 {
+            // Random unitary as RZ(a) RY(b) RZ(c) times a phase
+            let [a, b, c, phi] = [0; 4].map(|_| rng.r#gen::<f64>() * 6.0);
+            let u = rz_gate(a).dot(&ry_gate(b)).dot(&rz_gate(c)) * Complex64::from_polar(1.0, phi);
+            let mut p = q.clone();
+            p.apply1q_exact(&u, target).u1q_dag(&u, target);
+            assert!(p.isclose(&q));
+        }
+        // S† is S dagger
+        let mut p = ket("+");
+        assert!(p.u1q_dag(&S_GATE, 0).isclose(&ket("+").sdg(0)));
+    }
+
+    // -- General k-qubit gates --
+
+    #[test]
+    fn test_apply_nq_matches_apply2q() {
+        let q = random(4, &mut StdRng::seed_from_u64(67));
+        let cu = cp_gate(0.7).dot(&*CNOT_GATE);
+        for (a, b) in [(0, 1), (1, 0), (3, 1), (0, 2)] {
+            let mut nq = q.clone();
+            nq.apply_nq(&cu, &[a, b]);
+            let mut two = q.clone();
+            two.apply2q_exact(&cu, a, b);
+            assert!(nq.isclose(&two), "qubits ({a}, {b})");
+        }
+        // Single-qubit gates through apply_nq
+        let mut nq = q.clone();
+        assert!(nq.apply_nq(&H_GATE, &[2]).isclose(&q.clone().h(2)));
+    }
+
+    #[test]
+    fn test_apply_nq_toffoli() {
+        // CCX with qubits [c0, c1, target]: swaps rows 6 and 7
+        let mut ccx = Array2::from_diag_elem(8, ONE);
+        ccx.swap([6, 6], [6, 7]);
+        ccx.swap([7, 7], [7, 6]);
+        let q = random(4, &mut StdRng::seed_from_u64(71));
+        let mut nq = q.clone();
+        nq.apply_nq(&ccx, &[3, 0, 2]);
+        let mut expected = q.clone();
+        expected.mcx(&[3, 0], 2);
+        assert!(nq.isclose(&expected));
+    }
+
+    #[test]
+    fn test_apply_nq_scratch_reuse() {
+        let mut q = zeros(5).h(0);
+        let mut scratch = Vec::new();
+        for c in 0..4 {
+            q.apply_nq_with_scratch(&CNOT_GATE, &[c, c + 1], &mut scratch);
+        }
+        assert!(q.isclose(&ket("00000").h(0).cnot(0, 1).cnot(1, 2).cnot(2, 3).cnot(3, 4)));
+        // The scratch holds a full-size buffer for the next call
+        assert!(scratch.capacity() >= 32);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 4x4")]
+    fn test_apply_nq_wrong_size() {
+        zeros(2).apply_nq(&H_GATE, &[0, 1]);
+    }
+
+    // -- Flat gate matrices --
+
+    #[test]
+    fn test_apply_flat_matches_ndarray() {
+        let h: [Complex64; 4] = std::array::from_fn(|k| H_GATE[[k / 2, k % 2]]);
+        let cnot: [Complex64; 16] = std::array::from_fn(|k| CNOT_GATE[[k / 4, k % 4]]);
+        let start = random(3, &mut StdRng::seed_from_u64(69));
+
+        let mut flat = start.clone();
+        flat.apply1q_flat(&h, 1).apply2q_flat(&cnot, 1, 2);
+        let mut nd = start;
+        nd.apply1q(&H_GATE, 1).apply2q(&CNOT_GATE, 1, 2);
+        assert_eq!(flat.v, nd.v);
+    }
+
+    // -- Exact gate application --
+
+    #[test]
+    fn test_apply_exact_keeps_tiny_amplitudes() {
+        // Pair (|10>, |11>) has combined magnitude below SKIP_TOL
+        let (a, b) = (3e-9, 4e-9);
+        let q = QReg::new(vec![ONE, ZERO, Complex64::new(a, 0.0), Complex64::new(b, 0.0)]);
+
+        let mut exact = q.clone();
+        exact.apply1q_exact(&H_GATE, 0);
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((exact.v[2].re - s * (a + b)).abs() < 1e-20);
+        assert!((exact.v[3].re - s * (a - b)).abs() < 1e-20);
+
+        // The fast path leaves the pair untouched
+        let mut fast = q.clone();
+        fast.apply1q(&H_GATE, 0);
+        assert_eq!(fast.v[2].re, a);
+
+        // Exact H twice is the identity, even for tiny amplitudes
+        exact.apply1q_exact(&H_GATE, 0);
+        assert!((exact.v[2].re - a).abs() < 1e-20 && (exact.v[3].re - b).abs() < 1e-20);
+
+        let mut exact2 = q.clone();
+        exact2.apply2q_exact(&CNOT_GATE, 1, 0);
+        assert!((exact2.v[3].re - a).abs() < 1e-20 && (exact2.v[2].re - b).abs() < 1e-20);
+    }
+
+    // -- Serial and parallel paths agree --
+
+    #[test]
+    fn test_serial_parallel_identical() {
+        let run = || {
+            let mut q = random(6, &mut StdRng::seed_from_u64(41));
+            q.apply1q(&H_GATE, 2).apply2q(&CNOT_GATE, 0, 5);
+            q.mcx(&[1, 3], 4).cswap(0, 2, 3);
+            q
+        };
+        let parallel = with_par_threshold(0, run);
+        let serial = with_par_threshold(usize::MAX, run);
+        assert_eq!(parallel.v, serial.v);
+    }
+
+    #[test]
+    fn test_measure_serial_parallel_identical() {
+        let run = || {
+            let mut rng = StdRng::seed_from_u64(43);
+            let mut q = random(8, &mut rng);
+            let outcomes: Vec<usize> = (0..8).flat_map(|i| q.measure(i, 2, &mut rng)).collect();
+            (outcomes, q)
+        };
+        let (par_outcomes, parallel) = with_par_threshold(0, run);
+        let (ser_outcomes, serial) = with_par_threshold(usize::MAX, run);
+        assert_eq!(par_outcomes, ser_outcomes);
+        assert!(parallel.isclose(&serial));
+    }
+
+    #[test]
+    fn test_with_par_threshold_is_scoped() {
+        let before = par_threshold();
+        with_par_threshold(0, || {
+            assert_eq!(par_threshold(), 0);
+            with_par_threshold(7, || assert_eq!(par_threshold(), 7));
+            assert_eq!(par_threshold(), 0);
+            // Other threads keep the process-wide value
+            let other = std::thread::spawn(par_threshold).join().unwrap();
+            assert_eq!(other, before);
+        });
+        assert_eq!(par_threshold(), before);
+    }
+
+    // -- Conversions --
+
+    #[test]
+    fn test_from_str() {
+        let q: QReg = "+".into();
+        assert!(q.isclose(&ket("+")));
+        assert!(QReg::from("01*2").isclose(&ket("0101")));
+        assert!(QReg::from(String::from("1-")).isclose(&ket("1-")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid character '2'")]
+    fn test_from_str_invalid() {
+        let _ = QReg::from("2");
+    }
+
+    // -- Gate values --
+
+    #[test]
+    fn test_apply_gate_bell_circuit() {
+        let bell = vec![Gate::H { target: 0 }, Gate::Cnot { control: 0, target: 1 }];
+        let mut q = zeros(2);
+        for g in &bell {
+            q.apply_gate(g);
+        }
+        assert!(q.isclose(&ket("00").h(0).cnot(0, 1)));
+
+        let mut q = zeros(1);
+        q.apply_gate(&Gate::H { target: 0 })
+            .apply_gate(&Gate::Rz { theta: std::f64::consts::PI, target: 0 });
+        assert!(q.isclose_up_to_phase(&ket("-")));
+    }
+
+    #[test]
+    fn test_run_gate_list() {
+        let program = vec![Gate::H { target: 0 }, Gate::Cnot { control: 0, target: 1 }];
+        let mut q = zeros(2);
+        q.run(&program);
+        assert!(q.isclose(&ket("00").h(0).cnot(0, 1)));
+        assert!(zeros(2).run(&[]).isclose(&zeros(2)));
+    }
+
+    // -- Gates by name --
+
+    #[test]
+    fn test_apply_named() {
+        let mut q = zeros(2);
+        q.apply_named("h", &[0]).unwrap().apply_named("CNOT", &[0, 1]).unwrap();
+        assert!(q.isclose(&ket("00").h(0).cnot(0, 1)));
+        let mut q = ket("011");
+        q.apply_named("toffoli", &[0, 1, 2]).unwrap();
+        assert!(q.isclose(&ket("111")));
+    }
+
+    #[test]
+    fn test_apply_named_errors() {
+        let mut q = zeros(2);
+        assert_eq!(q.apply_named("foo", &[0]).unwrap_err(), "Unknown gate 'foo'");
+        assert_eq!(
+            q.apply_named("cx", &[0]).unwrap_err(),
+            "Gate 'cx' acts on 2 qubit(s), got 1"
+        );
+        assert_eq!(q.apply_named("x", &[2]).unwrap_err(), "Invalid qubit 2. Must be in [0, 2)");
+        assert_eq!(q.apply_named("cz", &[1, 1]).unwrap_err(), "Duplicate qubit 1");
+        assert!(q.isclose(&zeros(2)));
+    }
+
+    // -- Pauli strings --
+
+    #[test]
+    fn test_apply_pauli() {
+        let q = random(3, &mut StdRng::seed_from_u64(37));
+        let mut p = q.clone();
+        p.apply_pauli("IXZ");
+        assert!(p.isclose(&q.clone().z(0).x(1)));
+        let mut p = q.clone();
+        p.apply_pauli("YIX");
+        assert!(p.isclose(&q.clone().x(0).y(2)));
+        let mut p = q.clone();
+        assert!(p.apply_pauli("III").isclose(&q));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Pauli 'x'")]
+    fn test_apply_pauli_invalid() {
+        ket("00").apply_pauli("xI");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 2")]
+    fn test_apply_pauli_wrong_length() {
+        ket("00").apply_pauli("XYZ");
+    }
+
+    #[test]
+    fn test_pauli_rotation_zz() {
+        let theta = 0.9;
+        let q = random(2, &mut StdRng::seed_from_u64(47));
+        let mut evolved = q.clone();
+        evolved.pauli_rotation("ZZ", theta);
+        // RZZ(theta) = diag(e^{-i theta/2}, e^{i theta/2}, e^{i theta/2}, e^{-i theta/2})
+        let rzz = [-1.0, 1.0, 1.0, -1.0].map(|s| Complex64::from_polar(1.0, s * theta / 2.0));
+        for (i, phase) in rzz.iter().enumerate() {
+            assert!((evolved.v[i] - phase * q.v[i]).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_pauli_rotation_matches_closed_form() {
+        // P^2 = I, so exp(-i theta/2 P) = cos(theta/2) I - i sin(theta/2) P
+        let theta: f64 = 1.3;
+        let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+        let q = random(3, &mut StdRng::seed_from_u64(53));
+        for paulis in ["IIX", "YII", "XYZ", "ZIY", "YYI", "III"] {
+            let mut evolved = q.clone();
+            evolved.pauli_rotation(paulis, theta);
+            let mut p = q.clone();
+            p.apply_pauli(paulis);
+            let expected = &q.v * Complex64::new(c, 0.0) + &p.v * Complex64::new(0.0, -s);
+            let close = evolved.v.iter().zip(expected.iter()).all(|(a, b)| (a - b).norm() < 1e-12);
+            assert!(close, "{paulis}");
+        }
+    }
+
+    // -- Gates on every qubit --
+
+    #[test]
+    fn test_h_all_uniform() {
+        assert!(zeros(3).h_all().isclose(&ket("+++")));
+        assert!(zeros(3).h_all().isclose_slice(&[1.0 / 8f64.sqrt(); 8]));
+        assert!(ket("010").x_all().isclose(&ket("101")));
+        assert!(ket("+-").z_all().isclose(&ket("-+")));
+    }
+
+    #[test]
+    fn test_apply1q_many() {
+        let mut q = zeros(3);
+        q.apply1q_many(&H_GATE, &[0, 1, 2]);
+        assert!(q.isclose(&uniform(3)));
+
+        // Scattered, repeated and more-than-one-group targets match
+        // sequential single-qubit passes
+        let m = rx_gate(0.3).dot(&T_GATE.view());
+        let start = random(6, &mut StdRng::seed_from_u64(67));
+        let targets = [5, 0, 3, 0, 1, 2, 4, 5, 3];
+        let mut fused = start.clone();
+        fused.apply1q_many(&m, &targets);
+        let mut sequential = start;
+        for &t in &targets {
+            sequential.apply1q_exact(&m, t);
+        }
+        assert!(fused.isclose(&sequential));
+    }
+
+    // -- In-place gates --
+
+    #[test]
+    fn test_mut_gates_in_loop() {
+        struct Holder {
+            q: QReg,
+        }
+        let mut holder = Holder { q: zeros(4) };
+        for i in 0..4 {
+            holder.q.h_mut(i);
+        }
+        for i in 0..3 {
+            holder.q.cnot_mut(i, i + 1).z_mut(i);
+        }
+        let expected = zeros(4).h(0).h(1).h(2).h(3);
+        let expected = (0..3).fold(expected, |q, i| q.cnot(i, i + 1).z(i));
+        assert!(holder.q.isclose(&expected));
+        assert!(holder.q.x_mut(0).x_mut(0).isclose(&expected));
+    }
+
+    // -- CNOT on all 2-qubit basis states --
+
+    #[test]
+    fn test_cnot_all_basis() {
+        // CNOT(control=0, target=1): flips target when control is 1
+        assert_eq!(ket("00").cnot(0, 1).to_string(), "1.0|00>");
+        assert_eq!(ket("01").cnot(0, 1).to_string(), "1.0|11>");
+        assert_eq!(ket("10").cnot(0, 1).to_string(), "1.0|10>");
+        assert_eq!(ket("11").cnot(0, 1).to_string(), "1.0|01>");
+    }
+
+    // -- Controlled single-qubit gates --
+
+    #[test]
+    fn test_controlled_1q_matches_cnot() {
+        for i in 0..4 {
+            let mut q = basis(2, i);
+            q.controlled_1q(&X_GATE, 0, 1);
+            assert!(q.isclose(&basis(2, i).cnot(0, 1)), "input {i:02b}");
+        }
+        let mut q = ket("+-0");
+        q.controlled_1q(&X_GATE, 2, 0);
+        assert!(q.isclose(&ket("+-0").cnot(2, 0)));
+    }
+
+    #[test]
+    fn test_controlled_hadamard() {
+        // Control off: target untouched
+        let mut q = ket("00");
+        q.controlled_1q(&H_GATE, 1, 0);
+        assert!(q.isclose(&ket("00")));
+        // Control on: target gets H
+        let mut q = ket("10");
+        q.controlled_1q(&H_GATE, 1, 0);
+        assert!(q.isclose(&ket("1+")));
+        let mut q = ket("11");
+        q.controlled_1q(&H_GATE, 1, 0);
+        assert!(q.isclose(&ket("1-")));
+    }
+
+    #[test]
+    fn test_controlled_rz_phases() {
+        let mut q = ket("++");
+        q.controlled_1q(&rz_gate(0.7), 0, 1);
+        let mut expected = ket("++");
+        expected.v[1] *= Complex64::from_polar(1.0, -0.35);
+        expected.v[3] *= Complex64::from_polar(1.0, 0.35);
+        assert!(q.isclose(&expected));
+    }
+
+    // -- Multi-controlled X --
+
+    #[test]
+    fn test_mcx_truth_table() {
+        for i in 0..16 {
+            let mut q = basis(4, i);
+            q.mcx(&[0, 1, 2], 3);
+            let expected = if i & 0b0111 == 0b0111 { i ^ 0b1000 } else { i };
+            assert!(q.isclose(&basis(4, expected)), "input {i:04b}");
+        }
+    }
+
+    #[test]
+    fn test_mcx_matches_cnot() {
+        let mut q = ket("+0");
+        q.mcx(&[1], 0);
+        assert!(q.isclose(&ket("+0").cnot(1, 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Control and target must be different qubits")]
+    fn test_mcx_control_is_target() {
+        ket("000").mcx(&[0, 1], 1);
+    }
+
+    // -- Fredkin gate --
+
+    #[test]
+    fn test_cswap_truth_table() {
+        // control = qubit 2, swap qubits 0 and 1
+        for i in 0..8 {
+            let mut q = basis(3, i);
+            q.cswap(2, 0, 1);
+            let (c, a, b) = ((i >> 2) & 1, i & 1, (i >> 1) & 1);
+            let expected = if c == 1 { (c << 2) | (a << 1) | b } else { i };
+            assert!(q.isclose(&basis(3, expected)), "input {i:03b}");
+        }
+    }
+
+    #[test]
+    fn test_cswap_superposition() {
+        let mut q = ket("+01");
+        q.cswap(2, 0, 1);
+        assert!(q.isclose(&(ket("001") + ket("110"))));
+    }
+
+    // -- CPHASE gate tests --
+
+    #[test]
+    fn test_cphase_gate() {
+        // CPHASE only flips sign of |11>
+        assert_eq!(ket("00").cphase(0, 1).to_string(), "1.0|00>");
+        assert_eq!(ket("01").cphase(0, 1).to_string(), "1.0|01>");
+        assert_eq!(ket("10").cphase(0, 1).to_string(), "1.0|10>");
+        assert_eq!(ket("11").cphase(0, 1).to_string(), "-1.0|11>");
+    }
+
+    // -- Givens rotation --
+
+    #[test]
+    fn test_givens_leaves_00_and_11() {
+        for s in ["00", "11"] {
+            let mut q = ket(s);
+            q.givens(0.9, 1, 0);
+            assert!(q.isclose(&ket(s)), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_givens_conserves_particle_number() {
+        let theta: f64 = 0.4;
+        // With a = qubit 1 high, |01> -> cos|01> + sin|10>
+        let mut q = ket("01");
+        q.givens(theta, 1, 0);
+        assert!((q.v[0b01] - theta.cos()).norm() < 1e-12);
+        assert!((q.v[0b10] - theta.sin()).norm() < 1e-12);
+
+        // Any state in span{|01>, |10>} stays there, on a spectator too
+        let start = ket("+") * superpose(&[(ONE, ket("01")), (Complex64::new(0.0, 2.0), ket("10"))]);
+        let mut q = start.clone();
+        q.givens(1.3, 1, 0);
+        let weight_one: f64 = [0b001, 0b010, 0b101, 0b110].iter().map(|&i| q.v[i].norm_sqr()).sum();
+        assert!((weight_one - 1.0).abs() < 1e-12);
+
+        q.givens(-1.3, 1, 0);
+        assert!(q.isclose(&start));
+    }
+
+    // -- Tensor product tests --
+
+    #[test]
+    fn test_tensor_products() {
+        assert!(ket("01").isclose(&(ket("0") * ket("1"))));
+        assert!(ket("10").isclose(&(ket("1") * ket("0"))));
+        assert!(ket("11").isclose(&(ket("1") * ket("1"))));
+    }
+
+    #[test]
+    fn test_tensor_product_bit_order() {
+        // The left operand is the high-order qubit: |0> ⊗ |1> has qubit 0 set
+        let q = ket("0") * ket("1");
+        assert!(q.isclose(&ket("01")));
+        assert!(!q.isclose(&ket("10")));
+        assert!(q.isclose(&basis(2, 1)));
+        // Concatenation holds for multi-qubit operands
+        for (a, b) in [("1", "00"), ("10", "+"), ("1-", "0i"), ("011", "1")] {
+            assert!((ket(a) * ket(b)).isclose(&ket(&format!("{a}{b}"))), "{a} * {b}");
+        }
+        // Gates on the right operand's qubits act on the low-order qubits
+        let q = ket("00") * ket("0").x(0);
+        assert!(q.isclose(&ket("000").x(0)));
     }
-}
 
-impl Sub for QReg {
-    type Output = QReg;
-    fn sub(self, other: QReg) -> QReg {
-        let inv_sqrt2 = Complex64::new(std::f64::consts::FRAC_1_SQRT_2, 0.0);
-        let v = (&self.v - &other.v).mapv(|x| x * inv_sqrt2);
-        QReg::from_array(v)
-    }
-}
+    // -- Normalization test --
 
-impl Mul for QReg {
-    type Output = QReg;
-    /// Tensor product of two quantum states.
-    fn mul(self, other: QReg) -> QReg {
-        QReg::from_array(kron(&self.v, &other.v))
+    #[test]
+    fn test_normalization() {
+        // QReg normalizes on construction
+        let q = QReg::new(vec![
+            Complex64::new(3.0, 0.0),
+            Complex64::new(4.0, 0.0),
+        ]);
+        assert!((q.norm() - 1.0).abs() < 1e-10);
     }
-}
 
-// ---- Convenience Functions ----
+    // -- Measurement: repeated after collapse (Python: s.M(0,3) == [0,0,0]) --
 
-/// Create a quantum ket state from a string specification.
-///
-/// Characters: '0' = |0>, '1' = |1>, '+' = |+>, '-' = |->
-///
-/// Examples: "0", "1", "00", "01", "++", "+-", "101"
-pub fn ket(vecstring: &str) -> QReg {
-    assert!(!vecstring.is_empty(), "vecstring cannot be empty");
+    #[test]
+    fn test_measure_repeated_after_collapse() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut q = ket("+");
+        let first = q.measure(0, 1, &mut rng);
+        // After collapse, all further measurements give the same result
+        let repeated = q.measure(0, 5, &mut rng);
+        assert_eq!(repeated, vec![first[0]; 5]);
+    }
 
-    let valid = ['0', '1', '+', '-'];
-    for ch in vecstring.chars() {
-        assert!(
-            valid.contains(&ch),
-            "Invalid character '{ch}' in vecstring. Valid: 0, 1, +, -"
-        );
+    #[test]
+    fn test_collapse_tiny_branch_renormalizes() {
+        // A branch whose reported probability has underflowed to zero must
+        // still come out as a finite, normalized state
+        let mut q = QReg::from_array_raw(array![Complex64::new(1.0, 0.0), Complex64::new(1e-9, 0.0)]);
+        q.collapse(0, 1, 0.0);
+        assert!(q.v.iter().all(|a| a.is_finite()));
+        assert!(q.isclose(&ket("1")));
     }
 
-    let qvec = |s: char| -> Array1<Complex64> {
-        match s {
-            '0' => Array1::from_vec(vec![ONE, ZERO]),
-            '1' => Array1::from_vec(vec![ZERO, ONE]),
-            '+' => Array1::from_vec(vec![S2, S2]),
-            '-' => Array1::from_vec(vec![S2, NEG_S2]),
-            _ => unreachable!(),
-        }
-    };
+    // -- Non-collapsing sampling --
 
-    let mut register = Array1::from_vec(vec![ONE]);
-    for ch in vecstring.chars().rev() {
-        register = kron(&qvec(ch), &register);
+    #[test]
+    fn test_sample_does_not_collapse() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let q = ket("+");
+        let shots = q.sample(0, 1000, &mut rng);
+        let ones = shots.iter().filter(|&&x| x == 1).count();
+        // Roughly balanced 0s and 1s
+        assert!((400..=600).contains(&ones), "got {ones} ones out of 1000");
+        // State is unchanged
+        assert!(q.isclose(&ket("+")));
     }
-    QReg::from_array(register)
-}
 
-// ---- Python Bindings ----
+    #[test]
+    fn test_cdf_sample_counts() {
+        let q = ket("+");
+        let (zeros, ones) = q.cdf_sample(0, 1000, &mut StdRng::seed_from_u64(70));
+        assert_eq!(zeros + ones, 1000);
+        assert!((400..=600).contains(&ones), "got {ones} ones out of 1000");
+        // Same draws as `sample` with the same seed
+        let shots = q.sample(0, 1000, &mut StdRng::seed_from_u64(70));
+        assert_eq!(shots.iter().sum::<usize>(), ones);
+        assert_eq!(ket("10").cdf_sample(1, 50, &mut StdRng::seed_from_u64(71)), (0, 50));
+    }
 
-#[cfg(feature = "pyo3")]
-mod python;
+    // -- Probabilities and marginals --
 
-// ---- Tests ----
+    #[test]
+    fn test_marginal() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        let m = bell.marginal(&[0]);
+        assert!((m[0] - 0.5).abs() < 1e-10 && (m[1] - 0.5).abs() < 1e-10);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::rngs::StdRng;
-    use rand::SeedableRng;
+        let q = random(3, &mut StdRng::seed_from_u64(31));
+        let full = q.marginal(&[0, 1, 2]);
+        for (a, b) in full.iter().zip(q.probabilities()) {
+            assert!((a - b).abs() < 1e-12);
+        }
 
-    // -- Utility tests --
+        // |1> on qubit 2 and |0> on qubit 0: marginal over [2, 0] is index 1
+        let m = ket("100").marginal(&[2, 0]);
+        assert_eq!(m, vec![0.0, 1.0, 0.0, 0.0]);
+    }
 
     #[test]
-    fn test_nqubits() {
-        assert_eq!(nqubits(2), 1);
-        assert_eq!(nqubits(4), 2);
-        assert_eq!(nqubits(16), 4);
+    fn test_argmax() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        let (idx, p) = bell.argmax();
+        assert!(idx == 0 || idx == 3);
+        assert!((p - 0.5).abs() < 1e-10);
+        // Exact ties resolve to the lowest index
+        assert_eq!(basis(3, 0).h(0).h(1).h(2).argmax().0, 0);
+        assert_eq!(ket("101").argmax(), (5, 1.0));
     }
 
     #[test]
-    fn test_conjugate_index() {
-        assert_eq!(conjugate_index(0, 0), 1); // |0> -> |1>
-        assert_eq!(conjugate_index(1, 0), 0); // |1> -> |0>
-        assert_eq!(conjugate_index(2, 1), 0); // |10> -> |00>
+    fn test_shannon_entropy() {
+        assert_eq!(ket("0").shannon_entropy(), 0.0);
+        assert_eq!(ket("1011").shannon_entropy(), 0.0);
+        for n in 1..=5 {
+            assert!((zeros(n).h_all().shannon_entropy() - n as f64).abs() < 1e-10);
+        }
+        assert!((ket("00").h(0).cnot(0, 1).shannon_entropy() - 1.0).abs() < 1e-10);
     }
 
-    // -- Ket construction tests --
+    // -- Projection --
 
     #[test]
-    fn test_ket_single_qubits() {
-        assert_eq!(ket("0").to_string(), "1.0|0>");
-        assert_eq!(ket("1").to_string(), "1.0|1>");
+    fn test_project_bell() {
+        let mut bell = ket("00").h(0).cnot(0, 1);
+        let p = bell.project(0, 1);
+        assert!((p - 0.5).abs() < 1e-10);
+        assert!(bell.isclose(&ket("11")));
+        // Projecting again onto the same outcome is certain
+        assert!((bell.project(1, 1) - 1.0).abs() < 1e-10);
     }
 
     #[test]
-    fn test_ket_two_qubits() {
-        assert_eq!(ket("00").to_string(), "1.0|00>");
-        assert_eq!(ket("10").to_string(), "1.0|10>");
-        assert_eq!(ket("11").to_string(), "1.0|11>");
+    #[should_panic(expected = "zero probability")]
+    fn test_project_impossible() {
+        ket("00").project(0, 1);
     }
 
     #[test]
-    fn test_ket_three_qubits() {
-        assert_eq!(ket("101").to_string(), "1.0|101>");
+    fn test_measure_forced_bell() {
+        let mut bell = ket("00").h(0).cnot(0, 1);
+        bell.measure_forced(0, 1);
+        assert!(bell.isclose(&ket("11")));
+
+        let mut q = ket("++");
+        q.measure_forced(0, 0).measure_forced(1, 1);
+        assert!(q.isclose(&ket("10")));
     }
 
-    // -- Single-qubit gate tests --
+    // -- Counts histogram --
 
     #[test]
-    fn test_x_gate() {
-        assert_eq!(ket("0").x(0).to_string(), "1.0|1>");
-        assert_eq!(ket("1").x(0).to_string(), "1.0|0>");
-        assert_eq!(ket("00").x(0).to_string(), "1.0|01>");
-        assert_eq!(ket("01").x(1).to_string(), "1.0|11>");
+    fn test_sample_counts_bell() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let bell = ket("00").h(0).cnot(0, 1);
+        let counts = bell.sample_counts(1000, &mut rng);
+        let mut keys: Vec<_> = counts.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["00", "11"]);
+        assert_eq!(counts.values().sum::<usize>(), 1000);
+
+        // Probabilities summing to 0.5: overshooting draws stay on |10>
+        let q = QReg::from_array_raw(array![S2 * 0.5, ZERO, S2 * 0.5, ZERO]);
+        let counts = q.sample_counts(1000, &mut rng);
+        let mut keys: Vec<_> = counts.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["00", "10"]);
     }
 
+    // -- Noise channels --
+
     #[test]
-    fn test_chained_gates() {
-        assert_eq!(ket("00").x(0).x(1).to_string(), "1.0|11>");
-        assert_eq!(ket("+").h(0).x(0).to_string(), "1.0|1>");
+    fn test_depolarize_z_decay() {
+        // X and Y flip |0>, Z does not: <Z> = 1 - 4p/3
+        let mut rng = StdRng::seed_from_u64(21);
+        let p = 0.3;
+        let trials = 4000;
+        let mean_z: f64 = (0..trials)
+            .map(|_| {
+                let mut q = ket("0");
+                q.depolarize(0, p, &mut rng);
+                q.bloch_vector(0).2
+            })
+            .sum::<f64>()
+            / trials as f64;
+        let expected = 1.0 - 4.0 * p / 3.0;
+        assert!((mean_z - expected).abs() < 0.05, "<Z> = {mean_z}, expected {expected}");
     }
 
-    // -- Two-qubit gate tests --
-
     #[test]
-    fn test_cnot_gate() {
-        assert_eq!(ket("01").cnot(0, 1).to_string(), "1.0|11>");
-        assert_eq!(ket("00").cnot(0, 1).to_string(), "1.0|00>");
+    fn test_amplitude_damp_relaxation_rate() {
+        // |1> survives each step with probability 1 - gamma
+        let mut rng = StdRng::seed_from_u64(24);
+        let (gamma, steps, trials) = (0.1, 5, 4000);
+        let excited = (0..trials)
+            .filter(|_| {
+                let mut q = ket("1");
+                for _ in 0..steps {
+                    q.amplitude_damp(0, gamma, &mut rng);
+                }
+                q.prob0(0) < 0.5
+            })
+            .count();
+        let frac = excited as f64 / trials as f64;
+        let expected = (1.0f64 - gamma).powi(steps);
+        assert!((frac - expected).abs() < 0.05, "P(1) = {frac}, expected {expected}");
     }
 
-    // -- Isclose tests --
+    #[test]
+    fn test_amplitude_damp_extremes() {
+        let mut rng = StdRng::seed_from_u64(25);
+        for _ in 0..20 {
+            // gamma = 1 fully relaxes, whether or not the jump fires
+            let mut q = ket("0+");
+            q.amplitude_damp(0, 1.0, &mut rng);
+            assert!(q.isclose(&ket("00")));
+            // |0> never jumps and is unchanged
+            let mut q = ket("0");
+            q.amplitude_damp(0, 0.7, &mut rng);
+            assert!(q.isclose(&ket("0")));
+        }
+    }
 
     #[test]
-    fn test_isclose_plus_plus() {
-        assert!(ket("++").isclose_slice(&[0.5, 0.5, 0.5, 0.5]));
+    fn test_bit_flip_extremes() {
+        let mut rng = StdRng::seed_from_u64(22);
+        for _ in 0..20 {
+            let mut q = ket("0");
+            q.bit_flip(0, 1.0, &mut rng);
+            assert!(q.isclose(&ket("1")));
+            let mut q = ket("0");
+            q.bit_flip(0, 0.0, &mut rng);
+            assert!(q.isclose(&ket("0")));
+        }
     }
 
     #[test]
-    fn test_isclose_minus_minus() {
-        assert!(ket("--").isclose_slice(&[0.5, -0.5, -0.5, 0.5]));
+    fn test_phase_flip_extremes() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let mut q = ket("+");
+        q.phase_flip(0, 1.0, &mut rng);
+        assert!(q.isclose(&ket("-")));
+        q.phase_flip(0, 0.0, &mut rng);
+        assert!(q.isclose(&ket("-")));
     }
 
-    // -- Operator tests --
+    // -- Qubit permutation --
 
     #[test]
-    fn test_add_gives_plus() {
-        assert!(ket("+").isclose(&(ket("0") + ket("1"))));
+    fn test_permute_reverse() {
+        // Reversing 3 qubits moves bit 2 to bit 0
+        assert!(ket("110").permute(&[2, 1, 0]).isclose(&ket("011")));
+        assert!(ket("100").permute(&[2, 1, 0]).isclose(&ket("001")));
+        // Cyclic shift: new qubit 0 is old qubit 1, etc.
+        assert!(ket("001").permute(&[1, 2, 0]).isclose(&ket("100")));
     }
 
     #[test]
-    fn test_sub_gives_minus() {
-        assert!(ket("-").isclose(&(ket("0") - ket("1"))));
+    fn test_reverse_qubits() {
+        assert!(ket("100").reverse_qubits().isclose(&ket("001")));
+        assert!(ket("1+0").reverse_qubits().isclose(&ket("0+1")));
+        let q = random(3, &mut StdRng::seed_from_u64(12));
+        assert!(q.reverse_qubits().reverse_qubits().isclose(&q));
     }
 
     #[test]
-    fn test_tensor_product() {
-        let q00 = ket("0") * ket("0");
-        assert!(q00.isclose(&ket("00")));
+    fn test_extend_ancillas() {
+        assert!(ket("1").extend(2).isclose(&ket("100")));
+        assert!(ket("+1").extend(1).isclose(&ket("+10")));
+        let q = random(2, &mut StdRng::seed_from_u64(13));
+        assert!(q.extend(3).isclose(&(q.clone() * zeros(3))));
+        assert_eq!(q.extend(0).n, 2);
     }
 
-    // -- Measurement tests --
+    #[test]
+    fn test_permute_inverse_restores() {
+        let q = random(4, &mut StdRng::seed_from_u64(11));
+        let perm = [2, 0, 3, 1];
+        let mut inv = [0; 4];
+        for (i, &p) in perm.iter().enumerate() {
+            inv[p] = i;
+        }
+        assert!(q.permute(&perm).permute(&inv).isclose(&q));
+    }
 
     #[test]
-    fn test_measure_zero() {
-        let mut rng = StdRng::seed_from_u64(42);
-        let mut q = ket("0");
-        assert_eq!(q.measure(0, 1, &mut rng), vec![0]);
+    #[should_panic(expected = "Duplicate qubit 1")]
+    fn test_permute_invalid() {
+        ket("000").permute(&[0, 1, 1]);
     }
 
+    // -- Reduced density matrix --
+
     #[test]
-    fn test_measure_one() {
-        let mut rng = StdRng::seed_from_u64(42);
-        let mut q = ket("1");
-        assert_eq!(q.measure(0, 1, &mut rng), vec![1]);
+    fn test_reduced_density_matrix_bell() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        let rho = bell.reduced_density_matrix(&[0]);
+        let half = Complex64::new(0.5, 0.0);
+        assert_eq!(rho.dim(), (2, 2));
+        assert!((rho[[0, 0]] - half).norm() < 1e-10);
+        assert!((rho[[1, 1]] - half).norm() < 1e-10);
+        assert!(rho[[0, 1]].norm() < 1e-10);
+        assert!(rho[[1, 0]].norm() < 1e-10);
     }
 
     #[test]
-    fn test_measure_collapse() {
-        let mut rng = StdRng::seed_from_u64(42);
-        let mut q = ket("+");
-        let r1 = q.measure(0, 1, &mut rng);
-        // After collapse, subsequent measurements are deterministic
-        let r2 = q.measure(0, 3, &mut rng);
-        assert!(r2.iter().all(|&x| x == r1[0]));
+    fn test_density_matrix() {
+        let rho = ket("+").density_matrix();
+        assert!(rho.iter().all(|x| (x - Complex64::new(0.5, 0.0)).norm() < 1e-12));
+        let rho = random(3, &mut StdRng::seed_from_u64(5)).density_matrix();
+        assert_eq!(rho.dim(), (8, 8));
+        assert!((rho.diag().sum() - ONE).norm() < 1e-12);
+        // Keeping every qubit in order gives back the full density matrix
+        let q = ket("1i");
+        let full = q.reduced_density_matrix(&[0, 1]);
+        assert!(q.density_matrix().iter().zip(full.iter()).all(|(a, b)| (a - b).norm() < 1e-12));
     }
 
-    // -- ket terms() direct call (Python: ket('10').terms() == '1.0|10>') --
+    #[test]
+    fn test_reduced_density_matrix_product() {
+        // |0> on qubit 1, |+> on qubit 0: keeping qubit 0 gives |+><+|
+        let rho = ket("0+").reduced_density_matrix(&[0]);
+        assert!(rho.iter().all(|x| (x - Complex64::new(0.5, 0.0)).norm() < 1e-10));
+        // Keeping all qubits in reverse order permutes the basis
+        let rho = ket("01").reduced_density_matrix(&[1, 0]);
+        assert!((rho[[2, 2]] - ONE).norm() < 1e-10);
+    }
 
     #[test]
-    fn test_terms_direct() {
-        assert_eq!(ket("10").terms(), "1.0|10>");
+    #[should_panic(expected = "Duplicate qubit 0")]
+    fn test_reduced_density_matrix_duplicate() {
+        ket("00").reduced_density_matrix(&[0, 0]);
     }
 
-    // -- ket('01') missing from construction tests --
+    // -- Entanglement entropy --
 
     #[test]
-    fn test_ket_01() {
-        assert_eq!(ket("01").to_string(), "1.0|01>");
+    fn test_hermitian_eigenvalues() {
+        // Pauli-Y has eigenvalues -1 and +1
+        let eig = hermitian_eigenvalues(&Y_GATE);
+        assert!((eig[0] + 1.0).abs() < 1e-10);
+        assert!((eig[1] - 1.0).abs() < 1e-10);
     }
 
-    // -- Y gate tests --
+    #[test]
+    fn test_entanglement_entropy() {
+        assert!(ket("0+").entanglement_entropy(&[0]).abs() < 1e-8);
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert!((bell.entanglement_entropy(&[0]) - 1.0).abs() < 1e-8);
+        assert!((bell.entanglement_entropy(&[1]) - 1.0).abs() < 1e-8);
+        // GHZ: any single qubit against the rest carries one bit
+        let ghz = ket("000").h(0).cnot(0, 1).cnot(1, 2);
+        assert!((ghz.entanglement_entropy(&[0, 2]) - 1.0).abs() < 1e-8);
+        // Both sides of a bipartition of a pure state have equal entropy
+        let q = random(4, &mut StdRng::seed_from_u64(3));
+        let (a, b) = (q.entanglement_entropy(&[0, 2]), q.entanglement_entropy(&[1, 3]));
+        assert!((a - b).abs() < 1e-8, "{a} != {b}");
+    }
+
+    // -- Entanglement measures --
 
     #[test]
-    fn test_y_gate() {
-        // Y|0> = i|1>
-        let q = ket("0").y(0);
-        assert!(q.v[0].norm() < 1e-8);
-        assert!((q.v[1] - Complex64::new(0.0, 1.0)).norm() < 1e-5);
+    fn test_purity() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert!((bell.purity(&[0]) - 0.5).abs() < 1e-12);
+        assert!((bell.purity(&[0, 1]) - 1.0).abs() < 1e-12);
+        assert!((ket("+0i").purity(&[1]) - 1.0).abs() < 1e-12);
+        assert!((ket("+0i").purity(&[2, 0]) - 1.0).abs() < 1e-12);
+        let ghz = ket("000").h(0).cnot(0, 1).cnot(1, 2);
+        assert!((ghz.purity(&[0, 2]) - 0.5).abs() < 1e-12);
+    }
 
-        // Y|1> = -i|0>
-        let q = ket("1").y(0);
-        assert!((q.v[0] - Complex64::new(0.0, -1.0)).norm() < 1e-5);
-        assert!(q.v[1].norm() < 1e-8);
+    #[test]
+    fn test_is_product() {
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert!(!bell.is_product(&[0], 1e-8));
+        assert!(!bell.is_product(&[1], 1e-8));
+        assert!(ket("01").is_product(&[0], 1e-8));
+        // Bell pair on qubits 0,1 with qubit 2 separate
+        let q = ket("+") * bell;
+        assert!(q.is_product(&[2], 1e-8));
+        assert!(q.is_product(&[0, 1], 1e-8));
+        assert!(!q.is_product(&[0, 2], 1e-8));
     }
 
-    // -- Z gate tests --
+    #[test]
+    fn test_concurrence() {
+        assert!((ket("00").h(0).cnot(0, 1).concurrence() - 1.0).abs() < 1e-12);
+        assert!((ket("01").h(0).cnot(0, 1).z(1).concurrence() - 1.0).abs() < 1e-12);
+        assert!(ket("+i").concurrence().abs() < 1e-12);
+        assert!(ket("10").concurrence().abs() < 1e-12);
+        // Partially entangled: cos(a)|00> + sin(a)|11> has C = sin(2a)
+        let q = ket("00").ry(0.6, 0).cnot(0, 1);
+        assert!((q.concurrence() - 0.6f64.sin()).abs() < 1e-12);
+    }
 
     #[test]
-    fn test_z_gate() {
-        // Z|0> = |0>
-        assert_eq!(ket("0").z(0).to_string(), "1.0|0>");
-        // Z|1> = -|1>
-        assert_eq!(ket("1").z(0).to_string(), "-1.0|1>");
+    #[should_panic(expected = "exactly 2 qubits")]
+    fn test_concurrence_wrong_size() {
+        ket("000").concurrence();
     }
 
-    // -- H gate tests --
+    // -- Bloch vector --
 
     #[test]
-    fn test_h_gate() {
-        // H|0> = |+>
-        assert!(ket("0").h(0).isclose(&ket("+")));
-        // H|1> = |->
-        assert!(ket("1").h(0).isclose(&ket("-")));
-        // H|+> = |0>
-        assert!(ket("+").h(0).isclose(&ket("0")));
-        // H|-> = |1>
-        assert!(ket("-").h(0).isclose(&ket("1")));
-        // HH = I (self-inverse)
-        assert!(ket("0").h(0).h(0).isclose(&ket("0")));
-        assert!(ket("1").h(0).h(0).isclose(&ket("1")));
+    fn test_bloch_vector() {
+        let close = |(x, y, z): (f64, f64, f64), e: (f64, f64, f64)| {
+            (x - e.0).abs() < 1e-10 && (y - e.1).abs() < 1e-10 && (z - e.2).abs() < 1e-10
+        };
+        assert!(close(ket("0").bloch_vector(0), (0.0, 0.0, 1.0)));
+        assert!(close(ket("1").bloch_vector(0), (0.0, 0.0, -1.0)));
+        assert!(close(ket("+").bloch_vector(0), (1.0, 0.0, 0.0)));
+        assert!(close(ket("i").bloch_vector(0), (0.0, 1.0, 0.0)));
+        assert!(close(ket("-0").bloch_vector(1), (-1.0, 0.0, 0.0)));
+        // A qubit of a Bell state is maximally mixed
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert!(close(bell.bloch_vector(1), (0.0, 0.0, 0.0)));
     }
 
-    // -- S gate tests --
+    // -- Quantum Fourier Transform --
 
     #[test]
-    fn test_s_gate() {
-        // S|0> = |0>
-        assert_eq!(ket("0").s(0).to_string(), "1.0|0>");
-        // S|1> = i|1>
-        let q = ket("1").s(0);
-        assert!(q.v[0].norm() < 1e-8);
-        assert!((q.v[1] - Complex64::new(0.0, 1.0)).norm() < 1e-5);
+    fn test_qft_zeros_is_uniform() {
+        let q = zeros(3).qft();
+        let amp = 1.0 / 8f64.sqrt();
+        assert!(q.isclose_slice(&[amp; 8]));
     }
 
-    // -- CNOT on all 2-qubit basis states --
+    #[test]
+    fn test_qft_basis_phases() {
+        // QFT|j> = sum_k e^{2 pi i jk/N} |k> / sqrt(N)
+        let (n, j) = (3, 3);
+        let len = 1usize << n;
+        let q = basis(n, j).qft();
+        for k in 0..len {
+            let phase = 2.0 * std::f64::consts::PI * (j * k) as f64 / len as f64;
+            let expected = Complex64::from_polar(1.0 / (len as f64).sqrt(), phase);
+            assert!((q.v[k] - expected).norm() < 1e-10, "amplitude {k}");
+        }
+    }
 
     #[test]
-    fn test_cnot_all_basis() {
-        // CNOT(control=0, target=1): flips target when control is 1
-        assert_eq!(ket("00").cnot(0, 1).to_string(), "1.0|00>");
-        assert_eq!(ket("01").cnot(0, 1).to_string(), "1.0|11>");
-        assert_eq!(ket("10").cnot(0, 1).to_string(), "1.0|10>");
-        assert_eq!(ket("11").cnot(0, 1).to_string(), "1.0|01>");
+    fn test_qft_iqft_round_trip() {
+        let q = random(4, &mut StdRng::seed_from_u64(5));
+        assert!(q.clone().qft().iqft().isclose(&q));
+        assert!(q.clone().iqft().qft().isclose(&q));
     }
 
-    // -- CPHASE gate tests --
+    // -- Grover diffusion --
 
     #[test]
-    fn test_cphase_gate() {
-        // CPHASE only flips sign of |11>
-        assert_eq!(ket("00").cphase(0, 1).to_string(), "1.0|00>");
-        assert_eq!(ket("01").cphase(0, 1).to_string(), "1.0|01>");
-        assert_eq!(ket("10").cphase(0, 1).to_string(), "1.0|10>");
-        assert_eq!(ket("11").cphase(0, 1).to_string(), "-1.0|11>");
-    }
+    fn test_grover_two_qubits() {
+        // One Grover iteration on 2 qubits finds the marked state exactly
+        let start = ket("00").h(0).h(1);
+        // Oracle marking |11> is CPHASE
+        let q = start.cphase(0, 1).diffusion();
+        assert!(q.isclose(&ket("11")));
 
-    // -- Tensor product tests --
+        // Marking |01> with X-conjugated CPHASE
+        let q = ket("00").h(0).h(1).x(1).cphase(0, 1).x(1).diffusion();
+        assert!(q.isclose(&ket("01")));
+    }
 
     #[test]
-    fn test_tensor_products() {
-        assert!(ket("01").isclose(&(ket("0") * ket("1"))));
-        assert!(ket("10").isclose(&(ket("1") * ket("0"))));
-        assert!(ket("11").isclose(&(ket("1") * ket("1"))));
+    fn test_diffusion_fixes_uniform() {
+        // |s> is a +1 eigenstate of 2|s><s| - I
+        let s = ket("+++");
+        assert!(s.clone().diffusion().isclose(&s));
     }
 
-    // -- Normalization test --
+    // -- Hadamard test --
 
     #[test]
-    fn test_normalization() {
-        // QReg normalizes on construction
-        let q = QReg::new(vec![
-            Complex64::new(3.0, 0.0),
-            Complex64::new(4.0, 0.0),
-        ]);
-        assert!((q.norm() - 1.0).abs() < 1e-10);
+    fn test_hadamard_test_plus_z() {
+        // <+|Z|+> = 0; the estimate has standard deviation 1/sqrt(nshots)
+        let mut rng = StdRng::seed_from_u64(64);
+        let est = ket("+").hadamard_test(&[Gate::Z { target: 0 }], false, &mut rng, 4000);
+        assert!(est.abs() < 4.0 / 4000f64.sqrt(), "estimate {est}");
     }
 
-    // -- Measurement: repeated after collapse (Python: s.M(0,3) == [0,0,0]) --
+    #[test]
+    fn test_hadamard_test_eigenstates() {
+        // Eigenstates give deterministic ancilla outcomes
+        let mut rng = StdRng::seed_from_u64(65);
+        let z = [Gate::Z { target: 0 }];
+        assert_eq!(ket("0").hadamard_test(&z, false, &mut rng, 100), 1.0);
+        assert_eq!(ket("1").hadamard_test(&z, false, &mut rng, 100), -1.0);
+        // <1|S|1> = i
+        let s = [Gate::S { target: 0 }];
+        assert_eq!(ket("1").hadamard_test(&s, true, &mut rng, 100), 1.0);
+        // Two-qubit gates: CNOT leaves |1+> unchanged, CZ negates |11>
+        let cx = [Gate::Cnot { control: 1, target: 0 }];
+        assert_eq!(ket("1+").hadamard_test(&cx, false, &mut rng, 100), 1.0);
+        let cz = [Gate::Cphase { control: 0, target: 1 }];
+        assert_eq!(ket("11").hadamard_test(&cz, false, &mut rng, 100), -1.0);
+    }
+
+    // -- Measure all qubits at once --
 
     #[test]
-    fn test_measure_repeated_after_collapse() {
+    fn test_measure_all_bell() {
         let mut rng = StdRng::seed_from_u64(42);
-        let mut q = ket("+");
-        let first = q.measure(0, 1, &mut rng);
-        // After collapse, all further measurements give the same result
-        let repeated = q.measure(0, 5, &mut rng);
-        assert_eq!(repeated, vec![first[0]; 5]);
+        for _ in 0..100 {
+            let mut bell = ket("00").h(0).cnot(0, 1);
+            let outcome = bell.measure_all(&mut rng);
+            // Only |00> or |11> can be observed
+            assert!(outcome == 0 || outcome == 3, "unexpected outcome {outcome}");
+            assert!((bell.v[outcome] - ONE).norm() < 1e-10);
+            assert!((bell.norm() - 1.0).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_measure_all_short_total_avoids_zero_amplitudes() {
+        // Probabilities sum to 0.5, so half the draws run past the total
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..100 {
+            let mut q = QReg::from_array_raw(array![S2 * 0.5, ZERO, S2 * 0.5, ZERO]);
+            let outcome = q.measure_all(&mut rng);
+            assert!(outcome == 0 || outcome == 2, "unexpected outcome {outcome}");
+        }
     }
 
     // -- XX = I (Pauli involutions) --
@@ -761,4 +4376,19 @@ mod tests {
             std::f64::consts::FRAC_1_SQRT_2
         ]));
     }
+
+    #[test]
+    fn test_ghz_builder() {
+        assert!(ghz(1).isclose(&ket("+")));
+        assert!(ghz(3).isclose(&ket("000").h(0).cnot(0, 1).cnot(1, 2)));
+        let q = ghz(5);
+        assert_eq!(q.support_size(1e-8), 2);
+        assert!((q.v[0] - S2).norm() < 1e-12);
+        assert!((q.v[0b11111] - S2).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_bell_builder() {
+        assert!(bell().isclose(&ket("00").h(0).cnot(0, 1)));
+    }
 }
@@ -1,6 +1,28 @@
-use rvecsim::ket;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rvecsim::{ket, with_par_threshold, zeros, Circuit, CNOT_GATE, H_GATE};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
+/// System allocator that counts bytes allocated, for the scratch-buffer benchmark.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
 fn main() {
     println!("rvecsim - Quantum Vector State Simulator\n");
 
@@ -16,11 +38,15 @@ fn main() {
     println!("\n--- Benchmark ---\n");
 
     for n in [10, 15, 18, 20, 22] {
-        let zeros: String = "0".repeat(n);
+        let zero_str: String = "0".repeat(n);
         let t0 = Instant::now();
-        let q = ket(&zeros);
+        let q = ket(&zero_str);
         let setup = t0.elapsed();
 
+        let t0 = Instant::now();
+        let _ = zeros(n);
+        let setup_zeros = t0.elapsed();
+
         // Apply H to all qubits, then CNOT chain -> GHZ state
         let t0 = Instant::now();
         let mut q = q.h(0);
@@ -31,12 +57,150 @@ fn main() {
 
         let total_amps = 1usize << n;
         println!(
-            "{:2} qubits ({:>8} amps): setup {:>8.2?}, gates {:>8.2?} (H + {} CNOTs)",
+            "{:2} qubits ({:>8} amps): setup {:>8.2?} (zeros {:>8.2?}), gates {:>8.2?} (H + {} CNOTs)",
             n,
             total_amps,
             setup,
+            setup_zeros,
             gates,
             n - 1,
         );
     }
+
+    // Serial vs parallel gate application
+    println!("\n--- Serial vs parallel (H on every qubit) ---\n");
+
+    for (n, reps) in [(4, 10_000), (20, 5)] {
+        let time_gates = |threshold: usize| with_par_threshold(threshold, || {
+            let mut q = zeros(n);
+            let t0 = Instant::now();
+            for _ in 0..reps {
+                for target in 0..n {
+                    q.apply1q(&H_GATE, target);
+                }
+            }
+            t0.elapsed() / reps
+        });
+        let serial = time_gates(usize::MAX);
+        let parallel = time_gates(0);
+        println!(
+            "{:2} qubits: serial {:>10.2?}, parallel {:>10.2?} per pass",
+            n, serial, parallel,
+        );
+    }
+
+    // Serial vs parallel measurement: the same seed gives the same outcomes
+    println!("\n--- Serial vs parallel measurement (22 qubits) ---\n");
+
+    let n = 22;
+    let mut plus = zeros(n);
+    for target in 0..n {
+        plus.apply1q(&H_GATE, target);
+    }
+    let time_measure = |threshold: usize| with_par_threshold(threshold, || {
+        let mut q = plus.clone();
+        let mut rng = StdRng::seed_from_u64(7);
+        let t0 = Instant::now();
+        let outcomes: Vec<usize> = (0..n).flat_map(|i| q.measure(i, 1, &mut rng)).collect();
+        (outcomes, t0.elapsed())
+    });
+    let (serial_outcomes, serial) = time_measure(usize::MAX);
+    let (parallel_outcomes, parallel) = time_measure(0);
+    assert_eq!(serial_outcomes, parallel_outcomes);
+    println!(
+        "measure each of {} qubits: serial {:>10.2?}, parallel {:>10.2?}",
+        n, serial, parallel,
+    );
+
+    // Out-of-place k-qubit gates: a reused scratch buffer avoids allocating
+    // a fresh 2^n output vector per gate
+    println!("\n--- apply_nq scratch reuse (CNOT blocks) ---\n");
+
+    let n = 18;
+    let blocks = 100;
+    let pairs: Vec<[usize; 2]> = (0..blocks).map(|b| [b % n, (b + 1) % n]).collect();
+    let mut fresh = zeros(n).h_all();
+    let a0 = ALLOCATED.load(Ordering::Relaxed);
+    let t0 = Instant::now();
+    for pair in &pairs {
+        fresh.apply_nq(&CNOT_GATE, pair);
+    }
+    let (t_fresh, mib_fresh) = (t0.elapsed(), (ALLOCATED.load(Ordering::Relaxed) - a0) >> 20);
+    let mut reused = zeros(n).h_all();
+    let mut scratch = Vec::new();
+    let a0 = ALLOCATED.load(Ordering::Relaxed);
+    let t0 = Instant::now();
+    for pair in &pairs {
+        reused.apply_nq_with_scratch(&CNOT_GATE, pair, &mut scratch);
+    }
+    let (t_reused, mib_reused) = (t0.elapsed(), (ALLOCATED.load(Ordering::Relaxed) - a0) >> 20);
+    assert!(fresh.isclose(&reused));
+    println!(
+        "{} qubits, {} blocks: fresh buffers {:>8.2?} ({} MiB allocated), scratch {:>8.2?} ({} MiB)",
+        n, blocks, t_fresh, mib_fresh, t_reused, mib_reused,
+    );
+
+    // Gate fusion: runs of single-qubit gates become one pass
+    println!("\n--- Gate fusion (H X H S T on every qubit) ---\n");
+
+    let n = 20;
+    let circuit = (0..n).fold(Circuit::new(n), |c, q| c.h(q).x(q).h(q).s(q).t(q));
+    let t0 = Instant::now();
+    let unfused = circuit.apply(zeros(n));
+    let t_unfused = t0.elapsed();
+    let t0 = Instant::now();
+    let fused = circuit.apply_fused(zeros(n));
+    let t_fused = t0.elapsed();
+    assert!(fused.isclose(&unfused));
+    println!(
+        "{} qubits, {} gates: unfused {:>8.2?}, fused {:>8.2?}",
+        n,
+        circuit.gates.len(),
+        t_unfused,
+        t_fused,
+    );
+
+    // Batched single-qubit gates: disjoint targets share memory passes
+    println!("\n--- apply1q_many vs separate apply1q (H on 3 qubits) ---\n");
+
+    for n in [16, 20, 22] {
+        let targets = [0, n / 2, n - 1];
+        let mut separate = zeros(n);
+        let t0 = Instant::now();
+        for &t in &targets {
+            separate.apply1q(&H_GATE, t);
+        }
+        let t_separate = t0.elapsed();
+        let mut batched = zeros(n);
+        let t0 = Instant::now();
+        batched.apply1q_many(&H_GATE, &targets);
+        let t_batched = t0.elapsed();
+        assert!(batched.isclose(&separate));
+        println!(
+            "{:2} qubits: separate {:>10.2?}, batched {:>10.2?}",
+            n, t_separate, t_batched,
+        );
+    }
+
+    // Product-state construction: ket fills one pre-sized vector, versus
+    // chaining tensor products one qubit at a time
+    println!("\n--- ket construction (\"+-01\" pattern) ---\n");
+
+    for n in [8, 16, 20] {
+        let spec: String = "+-01".chars().cycle().take(n).collect();
+        let t0 = Instant::now();
+        let single = ket(&spec);
+        let t_single = t0.elapsed();
+        let t0 = Instant::now();
+        let chained = spec
+            .chars()
+            .skip(1)
+            .fold(ket(&spec[..1]), |acc, ch| acc * ket(&ch.to_string()));
+        let t_chained = t0.elapsed();
+        assert!(single.isclose(&chained));
+        println!(
+            "{:2} qubits: single allocation {:>10.2?}, chained kron {:>10.2?}",
+            n, t_single, t_chained,
+        );
+    }
 }
@@ -1,28 +1,47 @@
 // PyO3 Python bindings for rvecsim
 //
 // Provides a Python API matching the original vecsim.py:
-// - ket('0') constructor
-// - Gate methods: X, Y, Z, H, S, CNOT, CPHASE (uppercase, method chaining)
-// - M for measurement
+// - ket('0'), basis(n, index), uniform(n), bell(), ghz(n), w_state(n),
+//   random(n) and QReg.from_amplitudes constructors
+// - Gate methods: X, Y, Z, H, Hall, S, RX, RY, RZ, P, CNOT, CPHASE, MCX, CSWAP
+//   (uppercase, method chaining)
+// - M, Mall, measure_basis for measurement; sample for non-collapsing shots
 // - Operators: +, -, *
 // - isclose() accepting QReg or list
+// - Pickling via __getstate__/__setstate__
+// - to_numpy() and density_matrix() return complex128 numpy arrays
 
 #![allow(non_snake_case)]
 
-use crate::{ket as rust_ket, QReg as RustQReg};
+use crate::{
+    basis as rust_basis, bell as rust_bell, ghz as rust_ghz, ket as rust_ket, nqubits_checked,
+    random as rust_random, try_ket, try_superpose, uniform as rust_uniform,
+    w_state as rust_w_state, QReg as RustQReg,
+};
+use ndarray::Array2;
 use num_complex::Complex64;
+use numpy::{PyArray1, PyArray2, ToPyArray};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use rand::thread_rng;
+use rand::rngs::StdRng;
+use rand::{thread_rng, SeedableRng};
+use std::collections::HashMap;
 
 /// Python wrapper for QReg
-#[pyclass(name = "QReg")]
+#[pyclass(name = "QReg", module = "rvecsim")]
 pub struct PyQReg {
     inner: RustQReg,
 }
 
 #[pymethods]
 impl PyQReg {
+    /// QReg() is the single-qubit |0> state, like ket(); needed so
+    /// pickle can create an instance before restoring its state
+    #[new]
+    fn py_new() -> Self {
+        PyQReg { inner: rust_ket("0") }
+    }
+
     // ---- Properties ----
 
     /// Number of qubits
@@ -37,12 +56,46 @@ impl PyQReg {
         self.inner.v.to_vec()
     }
 
+    /// State vector amplitudes as a numpy array of complex128 (a copy)
+    fn to_numpy<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<Complex64>> {
+        self.inner.v.to_pyarray(py)
+    }
+
     /// L2 norm of the state vector
     #[getter]
     fn norm(&self) -> f64 {
         self.inner.norm()
     }
 
+    /// Create a normalized register from a list of complex (or real)
+    /// amplitudes, whose length must be a power of 2
+    #[staticmethod]
+    fn from_amplitudes(amplitudes: Vec<Complex64>) -> PyResult<Self> {
+        let inner = RustQReg::try_new(amplitudes).map_err(PyValueError::new_err)?;
+        Ok(PyQReg { inner })
+    }
+
+    // ---- Pickling ----
+
+    /// Pickle state: (n, amplitudes)
+    fn __getstate__(&self) -> (usize, Vec<Complex64>) {
+        (self.inner.n, self.inner.v.to_vec())
+    }
+
+    fn __setstate__(&mut self, state: (usize, Vec<Complex64>)) -> PyResult<()> {
+        let (n, amplitudes) = state;
+        if nqubits_checked(amplitudes.len()) != Some(n) {
+            return Err(PyValueError::new_err(format!(
+                "Invalid pickled QReg: {} amplitudes for {} qubits",
+                amplitudes.len(),
+                n
+            )));
+        }
+        self.inner = RustQReg::try_new(amplitudes)
+            .map_err(|e| PyValueError::new_err(format!("Invalid pickled QReg: {}", e)))?;
+        Ok(())
+    }
+
     // ---- String representations ----
 
     fn __str__(&self) -> String {
@@ -57,6 +110,23 @@ impl PyQReg {
         self.inner.terms()
     }
 
+    /// Number of basis states with amplitude magnitude above tol
+    #[pyo3(signature = (tol=1e-8))]
+    fn support_size(&self, tol: f64) -> usize {
+        self.inner.support_size(tol)
+    }
+
+    /// Significant terms as a list of (bitstring, amplitude) tuples
+    #[pyo3(signature = (tol=1e-8))]
+    fn to_terms_vec(&self, tol: f64) -> Vec<(String, Complex64)> {
+        self.inner.to_terms_vec(tol)
+    }
+
+    /// LaTeX rendering used by Jupyter notebooks
+    fn _repr_latex_(&self) -> String {
+        format!("${}$", self.inner.to_latex())
+    }
+
     // ---- Single-qubit gates ----
 
     /// Apply Pauli-X (NOT) gate to target qubit
@@ -162,6 +232,22 @@ impl PyQReg {
         Ok(slf)
     }
 
+    /// Apply Hadamard gate to every qubit
+    fn Hall(slf: Py<Self>, py: Python<'_>) -> PyResult<Py<Self>> {
+        let mut inner = slf.borrow(py).inner.clone();
+
+        py.allow_threads(|| {
+            inner.apply1q_all(&crate::H_GATE);
+        });
+
+        {
+            let mut this = slf.borrow_mut(py);
+            this.inner = inner;
+        }
+
+        Ok(slf)
+    }
+
     /// Apply S (phase) gate to target qubit
     fn S(slf: Py<Self>, target: usize, py: Python<'_>) -> PyResult<Py<Self>> {
         let mut inner = {
@@ -187,6 +273,28 @@ impl PyQReg {
         Ok(slf)
     }
 
+    // ---- Rotation and phase gates ----
+
+    /// Apply an X-rotation by angle theta (radians) to target qubit
+    fn RX(slf: Py<Self>, theta: f64, target: usize, py: Python<'_>) -> PyResult<Py<Self>> {
+        Self::apply_matrix(slf, crate::rx_gate(theta), target, py)
+    }
+
+    /// Apply a Y-rotation by angle theta (radians) to target qubit
+    fn RY(slf: Py<Self>, theta: f64, target: usize, py: Python<'_>) -> PyResult<Py<Self>> {
+        Self::apply_matrix(slf, crate::ry_gate(theta), target, py)
+    }
+
+    /// Apply a Z-rotation by angle theta (radians) to target qubit
+    fn RZ(slf: Py<Self>, theta: f64, target: usize, py: Python<'_>) -> PyResult<Py<Self>> {
+        Self::apply_matrix(slf, crate::rz_gate(theta), target, py)
+    }
+
+    /// Apply a phase gate diag(1, e^{i phi}) to target qubit
+    fn P(slf: Py<Self>, phi: f64, target: usize, py: Python<'_>) -> PyResult<Py<Self>> {
+        Self::apply_matrix(slf, crate::p_gate(phi), target, py)
+    }
+
     // ---- Two-qubit gates ----
 
     /// Apply controlled-NOT gate
@@ -261,20 +369,240 @@ impl PyQReg {
         Ok(slf)
     }
 
+    /// Apply multi-controlled X gate: flip target when all controls are 1
+    fn MCX(slf: Py<Self>, controls: Vec<usize>, target: usize, py: Python<'_>) -> PyResult<Py<Self>> {
+        let mut inner = {
+            let this = slf.borrow(py);
+            if target >= this.inner.n {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid target qubit {}. Must be in [0, {})",
+                    target, this.inner.n
+                )));
+            }
+            for (j, &c) in controls.iter().enumerate() {
+                if c >= this.inner.n {
+                    return Err(PyValueError::new_err(format!(
+                        "Invalid control qubit {}. Must be in [0, {})",
+                        c, this.inner.n
+                    )));
+                }
+                if c == target {
+                    return Err(PyValueError::new_err(
+                        "Control and target must be different qubits",
+                    ));
+                }
+                if controls[..j].contains(&c) {
+                    return Err(PyValueError::new_err(format!(
+                        "Duplicate control qubit {}",
+                        c
+                    )));
+                }
+            }
+            this.inner.clone()
+        };
+
+        py.allow_threads(|| {
+            inner.mcx(&controls, target);
+        });
+
+        {
+            let mut this = slf.borrow_mut(py);
+            this.inner = inner;
+        }
+
+        Ok(slf)
+    }
+
+    /// Apply Fredkin (controlled-SWAP) gate: swap a and b when control is 1
+    fn CSWAP(slf: Py<Self>, control: usize, a: usize, b: usize, py: Python<'_>) -> PyResult<Py<Self>> {
+        let mut inner = {
+            let this = slf.borrow(py);
+            for (name, q) in [("control", control), ("swap", a), ("swap", b)] {
+                if q >= this.inner.n {
+                    return Err(PyValueError::new_err(format!(
+                        "Invalid {} qubit {}. Must be in [0, {})",
+                        name, q, this.inner.n
+                    )));
+                }
+            }
+            if control == a || control == b || a == b {
+                return Err(PyValueError::new_err(
+                    "Control and swap qubits must be distinct",
+                ));
+            }
+            this.inner.clone()
+        };
+
+        py.allow_threads(|| {
+            inner.cswap(control, a, b);
+        });
+
+        {
+            let mut this = slf.borrow_mut(py);
+            this.inner = inner;
+        }
+
+        Ok(slf)
+    }
+
     // ---- Measurement ----
 
     /// Measure qubit i, ntimes times (default 1)
     /// Returns list of measurement outcomes (0 or 1)
-    #[pyo3(signature = (i, ntimes=1))]
-    fn M(&mut self, i: usize, ntimes: usize) -> PyResult<Vec<usize>> {
+    /// Pass seed for reproducible outcomes
+    #[pyo3(signature = (i, ntimes=1, seed=None))]
+    fn M(&mut self, i: usize, ntimes: usize, seed: Option<u64>) -> PyResult<Vec<usize>> {
         if i >= self.inner.n {
             return Err(PyValueError::new_err(format!(
                 "Invalid qubit {}. Must be in [0, {})",
                 i, self.inner.n
             )));
         }
-        let mut rng = thread_rng();
-        Ok(self.inner.measure(i, ntimes, &mut rng))
+        Ok(match seed {
+            Some(seed) => self.inner.measure(i, ntimes, &mut StdRng::seed_from_u64(seed)),
+            None => self.inner.measure(i, ntimes, &mut thread_rng()),
+        })
+    }
+
+    /// Measure qubit in the 'x', 'y' or 'z' basis ntimes, collapsing the state
+    /// Outcome 0 is the +1 eigenstate (|0>, |+> or |i>), 1 the -1 eigenstate
+    #[pyo3(signature = (qubit, basis, ntimes=1, seed=None))]
+    fn measure_basis(
+        &mut self,
+        qubit: usize,
+        basis: char,
+        ntimes: usize,
+        seed: Option<u64>,
+    ) -> PyResult<Vec<usize>> {
+        if qubit >= self.inner.n {
+            return Err(PyValueError::new_err(format!(
+                "Invalid qubit {}. Must be in [0, {})",
+                qubit, self.inner.n
+            )));
+        }
+        if !matches!(basis, 'x' | 'y' | 'z') {
+            return Err(PyValueError::new_err(format!(
+                "Invalid measurement basis '{basis}'. Valid: x, y, z"
+            )));
+        }
+        Ok(match seed {
+            Some(seed) => {
+                self.inner.measure_basis(qubit, basis, ntimes, &mut StdRng::seed_from_u64(seed))
+            }
+            None => self.inner.measure_basis(qubit, basis, ntimes, &mut thread_rng()),
+        })
+    }
+
+    /// Sample qubit i, nshots times, without collapsing the state
+    /// Returns list of sampled outcomes (0 or 1)
+    /// Pass seed for reproducible outcomes
+    #[pyo3(signature = (qubit, nshots=1, seed=None))]
+    fn sample(&self, qubit: usize, nshots: usize, seed: Option<u64>) -> PyResult<Vec<usize>> {
+        if qubit >= self.inner.n {
+            return Err(PyValueError::new_err(format!(
+                "Invalid qubit {}. Must be in [0, {})",
+                qubit, self.inner.n
+            )));
+        }
+        Ok(match seed {
+            Some(seed) => self.inner.sample(qubit, nshots, &mut StdRng::seed_from_u64(seed)),
+            None => self.inner.sample(qubit, nshots, &mut thread_rng()),
+        })
+    }
+
+    /// Sample the full register nshots times without collapsing the state
+    /// Returns dict mapping bitstrings to counts
+    /// Pass seed for reproducible counts
+    #[pyo3(signature = (nshots=1, seed=None))]
+    fn sample_counts(&self, nshots: usize, seed: Option<u64>) -> HashMap<String, usize> {
+        match seed {
+            Some(seed) => self.inner.sample_counts(nshots, &mut StdRng::seed_from_u64(seed)),
+            None => self.inner.sample_counts(nshots, &mut thread_rng()),
+        }
+    }
+
+    /// Measure all qubits at once, collapsing the register to a basis state
+    /// Returns the integer index of the observed basis state
+    /// Pass seed for a reproducible outcome
+    #[pyo3(signature = (seed=None))]
+    fn Mall(&mut self, seed: Option<u64>) -> usize {
+        match seed {
+            Some(seed) => self.inner.measure_all(&mut StdRng::seed_from_u64(seed)),
+            None => self.inner.measure_all(&mut thread_rng()),
+        }
+    }
+
+    // ---- Reordering ----
+
+    /// Return a new QReg with the qubit order reversed
+    fn reverse_qubits(&self) -> PyQReg {
+        PyQReg {
+            inner: self.inner.reverse_qubits(),
+        }
+    }
+
+    /// Return a new QReg with every amplitude complex-conjugated
+    fn conjugate(&self) -> PyQReg {
+        PyQReg {
+            inner: self.inner.conjugate(),
+        }
+    }
+
+    // ---- Analysis ----
+
+    /// Most probable basis state as (index, probability)
+    fn argmax(&self) -> (usize, f64) {
+        self.inner.argmax()
+    }
+
+    /// Amplitude of the basis state given as a bitstring (e.g. '01')
+    fn amplitude(&self, bits: &str) -> PyResult<Complex64> {
+        if bits.len() != self.inner.n || !bits.chars().all(|c| c == '0' || c == '1') {
+            return Err(PyValueError::new_err(format!(
+                "Invalid bitstring '{}'. Expected {} characters of 0 or 1",
+                bits, self.inner.n
+            )));
+        }
+        Ok(self.inner.amplitude(bits))
+    }
+
+    /// Probability of the full outcome given as a bitstring (e.g. '01')
+    fn prob(&self, bits: &str) -> PyResult<f64> {
+        Ok(self.amplitude(bits)?.norm_sqr())
+    }
+
+    /// Density matrix |psi><psi| as a 2^n x 2^n numpy array of complex128
+    fn density_matrix<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<Complex64>> {
+        self.inner.density_matrix().to_pyarray(py)
+    }
+
+    /// Bloch vector (x, y, z) of a single qubit
+    fn bloch_vector(&self, qubit: usize) -> PyResult<(f64, f64, f64)> {
+        if qubit >= self.inner.n {
+            return Err(PyValueError::new_err(format!(
+                "Invalid qubit {}. Must be in [0, {})",
+                qubit, self.inner.n
+            )));
+        }
+        Ok(self.inner.bloch_vector(qubit))
+    }
+
+    /// Whether the state factorizes across the qubits in partition and the
+    /// rest (reduced-state purity within tol of 1)
+    #[pyo3(signature = (partition, tol=1e-8))]
+    fn is_product(&self, partition: Vec<usize>, tol: f64) -> PyResult<bool> {
+        for (j, &q) in partition.iter().enumerate() {
+            if q >= self.inner.n {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid qubit {}. Must be in [0, {})",
+                    q, self.inner.n
+                )));
+            }
+            if partition[..j].contains(&q) {
+                return Err(PyValueError::new_err(format!("Duplicate qubit {}", q)));
+            }
+        }
+        Ok(self.inner.is_product(&partition, tol))
     }
 
     // ---- Comparison ----
@@ -309,6 +637,35 @@ impl PyQReg {
         ))
     }
 
+    /// Check if this state equals another QReg up to a global phase
+    fn isclose_up_to_phase(&self, other: &PyQReg) -> bool {
+        self.inner.isclose_up_to_phase(&other.inner)
+    }
+
+    /// Fidelity |<self|other>|^2 between two pure states
+    fn fidelity(&self, other: &PyQReg) -> PyResult<f64> {
+        self.check_same_size(other)?;
+        Ok(self.inner.fidelity(&other.inner))
+    }
+
+    /// Trace distance sqrt(1 - |<self|other>|^2) between two pure states
+    fn trace_distance(&self, other: &PyQReg) -> PyResult<f64> {
+        self.check_same_size(other)?;
+        Ok(self.inner.trace_distance(&other.inner))
+    }
+
+    /// Approximate equality: same as isclose() with another QReg.
+    /// Not transitive, and a global phase difference makes states unequal.
+    /// Comparing with a non-QReg returns NotImplemented.
+    fn __eq__(&self, other: PyRef<PyQReg>) -> bool {
+        self.inner.isclose(&other.inner)
+    }
+
+    /// Number of amplitudes, 2^n
+    fn __len__(&self) -> usize {
+        self.inner.v.len()
+    }
+
     // ---- Operators ----
 
     /// Superposition: (|a> + |b>) / sqrt(2)
@@ -333,11 +690,57 @@ impl PyQReg {
     }
 }
 
+impl PyQReg {
+    /// Error unless `other` has the same number of qubits.
+    fn check_same_size(&self, other: &PyQReg) -> PyResult<()> {
+        if self.inner.n != other.inner.n {
+            return Err(PyValueError::new_err(format!(
+                "States must have the same number of qubits, got {} and {}",
+                self.inner.n, other.inner.n
+            )));
+        }
+        Ok(())
+    }
+
+    /// Validate `target`, then apply a single-qubit matrix without holding the GIL.
+    fn apply_matrix(
+        slf: Py<Self>,
+        m: Array2<Complex64>,
+        target: usize,
+        py: Python<'_>,
+    ) -> PyResult<Py<Self>> {
+        let mut inner = {
+            let this = slf.borrow(py);
+            if target >= this.inner.n {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid target qubit {}. Must be in [0, {})",
+                    target, this.inner.n
+                )));
+            }
+            this.inner.clone()
+        };
+
+        py.allow_threads(|| {
+            inner.apply1q(&m, target);
+        });
+
+        {
+            let mut this = slf.borrow_mut(py);
+            this.inner = inner;
+        }
+
+        Ok(slf)
+    }
+}
+
 /// Create a quantum ket state from a string specification
 ///
-/// Characters: '0' = |0>, '1' = |1>, '+' = |+>, '-' = |->
+/// Characters: '0' = |0>, '1' = |1>, '+' = |+>, '-' = |->,
+/// 'i' = (|0> + i|1>)/sqrt(2), 'j' = (|0> - i|1>)/sqrt(2)
 ///
-/// Examples: ket('0'), ket('1'), ket('00'), ket('++'), ket('101')
+/// A pattern followed by '*N' is repeated N times: ket('0*3') is ket('000')
+///
+/// Examples: ket('0'), ket('1'), ket('00'), ket('++'), ket('101'), ket('ij'), ket('01*3')
 ///
 /// Args:
 ///     vecstring: String specifying the quantum state (default: '0')
@@ -347,29 +750,117 @@ impl PyQReg {
 #[pyfunction]
 #[pyo3(signature = (vecstring="0"))]
 fn ket(vecstring: &str) -> PyResult<PyQReg> {
-    if vecstring.is_empty() {
-        return Err(PyValueError::new_err("vecstring cannot be empty"));
+    let inner = try_ket(vecstring).map_err(PyValueError::new_err)?;
+    Ok(PyQReg { inner })
+}
+
+/// Create the computational basis state |index> on n qubits
+///
+/// Examples: basis(3, 5) is |101>
+#[pyfunction]
+fn basis(n: usize, index: usize) -> PyResult<PyQReg> {
+    if n >= usize::BITS as usize || index >= 1usize << n {
+        return Err(PyValueError::new_err(format!(
+            "Basis index {} out of range for {} qubits",
+            index, n
+        )));
     }
+    Ok(PyQReg {
+        inner: rust_basis(n, index),
+    })
+}
 
-    let valid = ['0', '1', '+', '-'];
-    for ch in vecstring.chars() {
-        if !valid.contains(&ch) {
-            return Err(PyValueError::new_err(format!(
-                "Invalid character '{}' in vecstring. Valid: 0, 1, +, -",
-                ch
-            )));
-        }
+/// Create the uniform superposition of all 2^n basis states
+///
+/// Equivalent to ket('0' * n).Hall() without n gate passes
+#[pyfunction]
+fn uniform(n: usize) -> PyResult<PyQReg> {
+    if n >= usize::BITS as usize {
+        return Err(PyValueError::new_err(format!("Too many qubits: {}", n)));
     }
+    Ok(PyQReg {
+        inner: rust_uniform(n),
+    })
+}
 
+/// Create the n-qubit GHZ state (|0...0> + |1...1>)/sqrt(2)
+#[pyfunction]
+fn ghz(n: usize) -> PyResult<PyQReg> {
+    if n == 0 || n >= usize::BITS as usize {
+        return Err(PyValueError::new_err(format!(
+            "Invalid qubit count {} for a GHZ state",
+            n
+        )));
+    }
     Ok(PyQReg {
-        inner: rust_ket(vecstring),
+        inner: rust_ghz(n),
     })
 }
 
+/// Create the Bell state (|00> + |11>)/sqrt(2)
+#[pyfunction]
+fn bell() -> PyQReg {
+    PyQReg { inner: rust_bell() }
+}
+
+/// Create the n-qubit W state, the equal superposition of all
+/// single-excitation basis states
+///
+/// Examples: w_state(3) is (|001> + |010> + |100>)/sqrt(3)
+#[pyfunction]
+fn w_state(n: usize) -> PyResult<PyQReg> {
+    if n == 0 || n >= usize::BITS as usize {
+        return Err(PyValueError::new_err(format!(
+            "Invalid qubit count {} for a W state",
+            n
+        )));
+    }
+    Ok(PyQReg {
+        inner: rust_w_state(n),
+    })
+}
+
+/// Create a Haar-random pure state on n qubits
+///
+/// Pass seed for a reproducible state
+#[pyfunction]
+#[pyo3(signature = (n, seed=None))]
+fn random(n: usize, seed: Option<u64>) -> PyResult<PyQReg> {
+    if n >= usize::BITS as usize {
+        return Err(PyValueError::new_err(format!("Too many qubits: {}", n)));
+    }
+    let inner = match seed {
+        Some(seed) => rust_random(n, &mut StdRng::seed_from_u64(seed)),
+        None => rust_random(n, &mut thread_rng()),
+    };
+    Ok(PyQReg { inner })
+}
+
+/// Create the weighted superposition sum_i c_i |psi_i>, normalized
+///
+/// Args:
+///     states: list of (coefficient, QReg) pairs with equal qubit counts
+#[pyfunction]
+fn superpose(states: Vec<(Complex64, PyRef<PyQReg>)>) -> PyResult<PyQReg> {
+    let pairs: Vec<(Complex64, RustQReg)> = states
+        .iter()
+        .map(|(c, q)| (*c, q.inner.clone()))
+        .collect();
+    let inner = try_superpose(&pairs).map_err(PyValueError::new_err)?;
+    Ok(PyQReg { inner })
+}
+
 /// Python module definition
 #[pymodule]
 fn rvecsim(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyQReg>()?;
     m.add_function(wrap_pyfunction!(ket, m)?)?;
+    m.add_function(wrap_pyfunction!(basis, m)?)?;
+    m.add_function(wrap_pyfunction!(uniform, m)?)?;
+    m.add_function(wrap_pyfunction!(ghz, m)?)?;
+    m.add_function(wrap_pyfunction!(bell, m)?)?;
+    m.add_function(wrap_pyfunction!(w_state, m)?)?;
+    m.add_function(wrap_pyfunction!(random, m)?)?;
+    m.add_function(wrap_pyfunction!(superpose, m)?)?;
     Ok(())
 }
@@ -0,0 +1,565 @@
+// Single-precision quantum register for rvecsim
+//
+// QReg32 stores amplitudes as Complex32, halving memory use relative to
+// QReg at the cost of precision (roughly 7 significant digits). It shares
+// the gate matrices and kernel structure of QReg, and measures, samples and
+// computes overlaps directly on the f32 amplitudes, so none of these widen
+// the state. Sums of probabilities and inner products are accumulated in
+// f64. Convert between the two with `QReg32::from(&q)` and `to_f64()`.
+
+use crate::{
+    conjugate_index, for_each_index, is_unitary, nqubits_checked, p_gate, qterm, round_sigfigs,
+    rx_gate, ry_gate, rz_gate, sum_over_indices, QReg, SendPtr, CNOT_GATE, CPHASE_GATE, H_GATE,
+    SDG_GATE, SKIP_TOL, S_GATE, TDG_GATE, T_GATE, X_GATE, Y_GATE, Z_GATE,
+};
+use ndarray::{Array1, Array2};
+use num_complex::{Complex32, Complex64};
+use rand::Rng;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+/// Quantum register with single-precision (f32) amplitudes.
+#[derive(Clone)]
+pub struct QReg32 {
+    pub v: Array1<Complex32>,
+    pub n: usize,
+}
+
+/// Narrow a gate matrix to single precision.
+fn to_c32(m: &Array2<Complex64>) -> Array2<Complex32> {
+    m.mapv(|c| Complex32::new(c.re as f32, c.im as f32))
+}
+
+/// Widen a single amplitude to double precision.
+fn to_c64(c: Complex32) -> Complex64 {
+    Complex64::new(c.re as f64, c.im as f64)
+}
+
+fn kron32(a: &Array1<Complex32>, b: &Array1<Complex32>) -> Array1<Complex32> {
+    let (la, lb) = (a.len(), b.len());
+    let mut result = Array1::zeros(la * lb);
+    for i in 0..la {
+        for j in 0..lb {
+            result[i * lb + j] = a[i] * b[j];
+        }
+    }
+    result
+}
+
+impl QReg32 {
+    /// Create a new register from a vector of complex amplitudes.
+    /// The vector length must be a power of 2. The state is normalized.
+    pub fn new(register: Vec<Complex32>) -> Self {
+        assert!(!register.is_empty(), "Register cannot be empty");
+        QReg32::from_array(Array1::from_vec(register))
+    }
+
+    /// Create a register from an array of amplitudes, normalizing it.
+    fn from_array(v: Array1<Complex32>) -> Self {
+        let len = v.len();
+        let n = nqubits_checked(len)
+            .unwrap_or_else(|| panic!("Register length must be power of 2, got {len}"));
+        let mut qreg = QReg32 { v, n };
+        qreg.normalize();
+        qreg
+    }
+
+    /// The n-qubit all-zeros state |00...0>, allocated directly in f32.
+    pub fn zeros(n: usize) -> Self {
+        assert!(n < usize::BITS as usize, "Too many qubits for a usize index: {n}");
+        let mut v = Array1::zeros(1usize << n);
+        v[0] = Complex32::new(1.0, 0.0);
+        QReg32 { v, n }
+    }
+
+    /// Widen to a double-precision `QReg`.
+    pub fn to_f64(&self) -> QReg {
+        QReg {
+            v: self.v.mapv(|c| Complex64::new(c.re as f64, c.im as f64)),
+            n: self.n,
+        }
+    }
+
+    /// Calculate the L2 norm of the state vector (accumulated in f64).
+    pub fn norm(&self) -> f64 {
+        self.v.iter().map(|x| x.norm_sqr() as f64).sum::<f64>().sqrt()
+    }
+
+    /// Normalize the state vector in-place.
+    pub fn normalize(&mut self) {
+        let norm = self.norm();
+        assert!(norm > 1e-10, "Cannot normalize zero vector");
+        let norm = norm as f32;
+        self.v.mapv_inplace(|x| x / norm);
+    }
+
+    /// Return string representation of significant terms in the quantum state.
+    /// Coefficients are shown to the 7 significant figures f32 can carry.
+    pub fn terms(&self) -> String {
+        let widen = |x: f32| round_sigfigs(x as f64, 7);
+        self.v
+            .iter()
+            .enumerate()
+            .filter(|(_, qi)| qi.norm() > 1e-6)
+            .map(|(i, qi)| qterm(i, Complex64::new(widen(qi.re), widen(qi.im)), self.n))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Check if this state is close to another, with a tolerance suited
+    /// to single precision.
+    pub fn isclose(&self, other: &QReg32) -> bool {
+        if self.v.len() != other.v.len() {
+            return false;
+        }
+        self.v
+            .iter()
+            .zip(other.v.iter())
+            .all(|(a, b)| (a - b).norm() < 1e-4)
+    }
+
+    /// Check if this state is close to a slice of f64 values (treated as
+    /// real), with the single-precision tolerance of `isclose`.
+    pub fn isclose_slice(&self, other: &[f64]) -> bool {
+        if self.v.len() != other.len() {
+            return false;
+        }
+        self.v
+            .iter()
+            .zip(other.iter())
+            .all(|(a, &b)| (to_c64(*a) - b).norm() < 1e-4)
+    }
+
+    /// Inner product <self|other>, accumulated in f64.
+    pub fn inner(&self, other: &QReg32) -> Complex64 {
+        assert!(
+            self.v.len() == other.v.len(),
+            "States must have the same length, got {} and {}",
+            self.v.len(),
+            other.v.len()
+        );
+        self.v
+            .iter()
+            .zip(other.v.iter())
+            .map(|(a, b)| to_c64(*a).conj() * to_c64(*b))
+            .sum()
+    }
+
+    /// Fidelity |<self|other>|^2 between two pure states.
+    pub fn fidelity(&self, other: &QReg32) -> f64 {
+        self.inner(other).norm_sqr()
+    }
+
+    /// Apply a single-qubit gate matrix to the target qubit. The matrix is
+    /// narrowed to f32, so the `QReg` gate constants can be used directly.
+    pub fn apply1q(&mut self, m: &Array2<Complex64>, target: usize) -> &mut Self {
+        assert!(
+            target < self.n,
+            "Invalid target qubit {target}. Must be in [0, {})",
+            self.n
+        );
+        debug_assert!(is_unitary(m, 1e-8), "Single-qubit gate matrix is not unitary");
+        let m = to_c32(m);
+        let (m00, m01, m10, m11) = (m[[0, 0]], m[[0, 1]], m[[1, 0]], m[[1, 1]]);
+        let skip_tol = SKIP_TOL as f32;
+        let len = self.v.len();
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each (i, j) pair is unique and non-overlapping, as in
+        // `QReg::apply1q`.
+        for_each_index(len, move |i| {
+            let j = conjugate_index(i, target);
+            if i > j {
+                return;
+            }
+            unsafe {
+                let qi = ptr.read(i);
+                let qj = ptr.read(j);
+                if qi.norm() + qj.norm() < skip_tol {
+                    return;
+                }
+                ptr.write(i, m00 * qi + m01 * qj);
+                ptr.write(j, m10 * qi + m11 * qj);
+            }
+        });
+        self
+    }
+
+    /// Apply a two-qubit gate matrix to the control and target qubits.
+    pub fn apply2q(
+        &mut self,
+        m: &Array2<Complex64>,
+        control: usize,
+        target: usize,
+    ) -> &mut Self {
+        assert!(
+            control < self.n,
+            "Invalid control qubit {control}. Must be in [0, {})",
+            self.n
+        );
+        assert!(
+            target < self.n,
+            "Invalid target qubit {target}. Must be in [0, {})",
+            self.n
+        );
+        assert!(control != target, "Control and target must be different qubits");
+
+        debug_assert!(is_unitary(m, 1e-8), "Two-qubit gate matrix is not unitary");
+        let m = to_c32(m);
+        let mv: [[Complex32; 4]; 4] =
+            std::array::from_fn(|r| std::array::from_fn(|c| m[[r, c]]));
+        let skip_tol = SKIP_TOL as f32;
+        let len = self.v.len();
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each (i, j, k, l) group is unique and non-overlapping, as
+        // in `QReg::apply2q`.
+        for_each_index(len, move |i| {
+            let j = conjugate_index(i, target);
+            if i > j {
+                return;
+            }
+            let k = conjugate_index(i, control);
+            if i > k {
+                return;
+            }
+            let l = conjugate_index(j, control);
+
+            unsafe {
+                let q = [ptr.read(i), ptr.read(j), ptr.read(k), ptr.read(l)];
+                if q.iter().map(|a| a.norm()).sum::<f32>() < skip_tol {
+                    return;
+                }
+                for (row, idx) in mv.iter().zip([i, j, k, l]) {
+                    ptr.write(idx, row[0] * q[0] + row[1] * q[1] + row[2] * q[2] + row[3] * q[3]);
+                }
+            }
+        });
+        self
+    }
+
+    // ---- Gate methods (consume self for chaining) ----
+
+    /// Apply Pauli-X (NOT) gate to target qubit.
+    pub fn x(mut self, target: usize) -> Self {
+        self.apply1q(&X_GATE, target);
+        self
+    }
+
+    /// Apply Pauli-Y gate to target qubit.
+    pub fn y(mut self, target: usize) -> Self {
+        self.apply1q(&Y_GATE, target);
+        self
+    }
+
+    /// Apply Pauli-Z gate to target qubit.
+    pub fn z(mut self, target: usize) -> Self {
+        self.apply1q(&Z_GATE, target);
+        self
+    }
+
+    /// Apply Hadamard gate to target qubit.
+    pub fn h(mut self, target: usize) -> Self {
+        self.apply1q(&H_GATE, target);
+        self
+    }
+
+    /// Apply S (phase) gate to target qubit.
+    pub fn s(mut self, target: usize) -> Self {
+        self.apply1q(&S_GATE, target);
+        self
+    }
+
+    /// Apply S-dagger (inverse phase) gate to target qubit.
+    pub fn sdg(mut self, target: usize) -> Self {
+        self.apply1q(&SDG_GATE, target);
+        self
+    }
+
+    /// Apply T (pi/8) gate to target qubit.
+    pub fn t(mut self, target: usize) -> Self {
+        self.apply1q(&T_GATE, target);
+        self
+    }
+
+    /// Apply T-dagger gate to target qubit.
+    pub fn tdg(mut self, target: usize) -> Self {
+        self.apply1q(&TDG_GATE, target);
+        self
+    }
+
+    /// Apply a Z-rotation by angle `theta` (radians) to target qubit.
+    pub fn rz(mut self, theta: f64, target: usize) -> Self {
+        self.apply1q(&rz_gate(theta), target);
+        self
+    }
+
+    /// Apply an X-rotation by angle `theta` (radians) to target qubit.
+    pub fn rx(mut self, theta: f64, target: usize) -> Self {
+        self.apply1q(&rx_gate(theta), target);
+        self
+    }
+
+    /// Apply a Y-rotation by angle `theta` (radians) to target qubit.
+    pub fn ry(mut self, theta: f64, target: usize) -> Self {
+        self.apply1q(&ry_gate(theta), target);
+        self
+    }
+
+    /// Apply a phase gate P(phi) = diag(1, e^{i phi}) to target qubit.
+    pub fn p(mut self, phi: f64, target: usize) -> Self {
+        self.apply1q(&p_gate(phi), target);
+        self
+    }
+
+    /// Apply controlled-NOT gate.
+    pub fn cnot(mut self, control: usize, target: usize) -> Self {
+        self.apply2q(&CNOT_GATE, control, target);
+        self
+    }
+
+    /// Apply controlled-phase gate.
+    pub fn cphase(mut self, control: usize, target: usize) -> Self {
+        self.apply2q(&CPHASE_GATE, control, target);
+        self
+    }
+
+    // ---- Measurement ----
+
+    /// Measure qubit `i` `ntimes` times, collapsing the state on the first
+    /// shot, as in `QReg::measure`.
+    pub fn measure(&mut self, i: usize, ntimes: usize, rng: &mut impl Rng) -> Vec<usize> {
+        assert!(i < self.n, "Invalid qubit {i}. Must be in [0, {})", self.n);
+
+        let mut prob0 = self.prob0(i);
+        let mut collapsed = false;
+
+        let mut results = Vec::with_capacity(ntimes);
+        for _ in 0..ntimes {
+            let outcome = if rng.r#gen::<f64>() < prob0 { 0 } else { 1 };
+            results.push(outcome);
+
+            if !collapsed {
+                let prob = if outcome == 0 { prob0 } else { 1.0 - prob0 };
+                self.collapse(i, outcome, prob);
+                prob0 = if outcome == 0 { 1.0 } else { 0.0 };
+                collapsed = true;
+            }
+        }
+        results
+    }
+
+    /// Sample qubit `qubit` `nshots` times without collapsing the state.
+    pub fn sample(&self, qubit: usize, nshots: usize, rng: &mut impl Rng) -> Vec<usize> {
+        assert!(qubit < self.n, "Invalid qubit {qubit}. Must be in [0, {})", self.n);
+
+        let prob0 = self.prob0(qubit);
+        (0..nshots)
+            .map(|_| if rng.r#gen::<f64>() < prob0 { 0 } else { 1 })
+            .collect()
+    }
+
+    /// Measure all qubits at once, collapsing the register to a basis state.
+    /// Returns the integer index of the observed basis state.
+    pub fn measure_all(&mut self, rng: &mut impl Rng) -> usize {
+        let r = rng.r#gen::<f64>();
+        let mut cumulative = 0.0;
+        // Rounding fallback: the last state that can actually be observed
+        let mut outcome = self
+            .v
+            .iter()
+            .rposition(|amp| amp.norm_sqr() > 0.0)
+            .unwrap_or(self.v.len() - 1);
+        for (idx, amp) in self.v.iter().enumerate() {
+            cumulative += amp.norm_sqr() as f64;
+            if r < cumulative {
+                outcome = idx;
+                break;
+            }
+        }
+
+        self.v.fill(Complex32::new(0.0, 0.0));
+        self.v[outcome] = Complex32::new(1.0, 0.0);
+        outcome
+    }
+
+    /// Probability |amp|^2 of each computational basis state.
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.v.iter().map(|amp| amp.norm_sqr() as f64).collect()
+    }
+
+    /// Bloch vector (<X>, <Y>, <Z>) of a single qubit, accumulated in f64
+    /// from the amplitude pairs that differ only in `qubit`.
+    pub fn bloch_vector(&self, qubit: usize) -> (f64, f64, f64) {
+        assert!(qubit < self.n, "Invalid qubit {qubit}. Must be in [0, {})", self.n);
+        let (mut rho00, mut rho11, mut rho01) = (0.0, 0.0, Complex64::new(0.0, 0.0));
+        for i in (0..self.v.len()).filter(|i| (i >> qubit) & 1 == 0) {
+            let (a0, a1) = (to_c64(self.v[i]), to_c64(self.v[i | (1 << qubit)]));
+            rho00 += a0.norm_sqr();
+            rho11 += a1.norm_sqr();
+            rho01 += a0 * a1.conj();
+        }
+        (2.0 * rho01.re, -2.0 * rho01.im, rho00 - rho11)
+    }
+
+    /// Probability of measuring |0> on qubit `i`.
+    fn prob0(&self, i: usize) -> f64 {
+        let v = &self.v;
+        sum_over_indices(v.len(), |idx| {
+            if (idx >> i) & 1 == 0 { v[idx].norm_sqr() as f64 } else { 0.0 }
+        })
+    }
+
+    /// Collapse `qubit` onto `outcome` with probability `prob`, zeroing the
+    /// inconsistent amplitudes and rescaling the rest, as `QReg` does.
+    fn collapse(&mut self, qubit: usize, outcome: usize, prob: f64) {
+        let scale = if prob > 1e-10 { (1.0 / prob.sqrt()) as f32 } else { 1.0 };
+        let ptr = SendPtr(self.v.as_mut_ptr());
+        // SAFETY: Each iteration reads and writes only its own index.
+        for_each_index(self.v.len(), move |idx| unsafe {
+            if (idx >> qubit) & 1 != outcome {
+                ptr.write(idx, Complex32::new(0.0, 0.0));
+            } else {
+                ptr.write(idx, ptr.read(idx) * scale);
+            }
+        });
+        if prob <= 1e-10 {
+            self.normalize();
+        }
+    }
+}
+
+/// Equal-weight superposition (|a> + |b>)/sqrt(2), normalized, as for `QReg`.
+impl Add for QReg32 {
+    type Output = QReg32;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: QReg32) -> QReg32 {
+        let v = (&self.v + &other.v).mapv(|x| x * std::f32::consts::FRAC_1_SQRT_2);
+        QReg32::from_array(v)
+    }
+}
+
+impl Sub for QReg32 {
+    type Output = QReg32;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: QReg32) -> QReg32 {
+        let v = (&self.v - &other.v).mapv(|x| x * std::f32::consts::FRAC_1_SQRT_2);
+        QReg32::from_array(v)
+    }
+}
+
+/// Tensor product `a * b` = |a> ⊗ |b>, with `a` as the high-order qubits
+/// as for `QReg`.
+impl Mul for QReg32 {
+    type Output = QReg32;
+    fn mul(self, other: QReg32) -> QReg32 {
+        QReg32::from_array(kron32(&self.v, &other.v))
+    }
+}
+
+impl From<&QReg> for QReg32 {
+    fn from(q: &QReg) -> Self {
+        QReg32 {
+            v: q.v.mapv(|c| Complex32::new(c.re as f32, c.im as f32)),
+            n: q.n,
+        }
+    }
+}
+
+impl fmt::Display for QReg32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.terms())
+    }
+}
+
+impl fmt::Debug for QReg32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QReg32")
+            .field("n", &self.n)
+            .field("terms", &self.terms())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ket;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_bell_matches_f64() {
+        let bell32 = QReg32::zeros(2).h(0).cnot(0, 1);
+        let bell = ket("00").h(0).cnot(0, 1);
+        assert!(bell32.to_f64().isclose(&bell));
+        assert_eq!(bell32.to_string(), "0.7071068|00> 0.7071068|11>");
+    }
+
+    #[test]
+    fn test_roundtrip_conversion() {
+        let q = ket("+-i1").t(1).cphase(0, 3);
+        let q32 = QReg32::from(&q);
+        assert!(q32.to_f64().isclose(&q));
+        assert!((q32.norm() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gates_track_f64() {
+        let q = ket("0110").h(0).s(1).y(2).cnot(0, 3).tdg(3).z(1).x(2).sdg(0);
+        let q32 = QReg32::from(&ket("0110")).h(0).s(1).y(2).cnot(0, 3).tdg(3).z(1).x(2).sdg(0);
+        assert!(q32.to_f64().isclose(&q));
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many qubits")]
+    fn test_zeros_too_many_qubits_panics() {
+        QReg32::zeros(64);
+    }
+
+    #[test]
+    fn test_measure_collapses() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut q = QReg32::zeros(2).h(0).cnot(0, 1);
+        let first = q.measure(0, 3, &mut rng);
+        assert_eq!(first, vec![first[0]; 3]);
+        // The Bell partner collapses with it
+        assert_eq!(q.sample(1, 5, &mut rng), vec![first[0]; 5]);
+        assert!((q.norm() - 1.0).abs() < 1e-6);
+
+        let mut q = QReg32::zeros(3).h(1);
+        let idx = q.measure_all(&mut rng);
+        assert!(idx == 0 || idx == 2);
+        assert!(q.isclose_slice(&q.probabilities()));
+    }
+
+    #[test]
+    fn test_operators_match_f64() {
+        let a = QReg32::from(&ket("0"));
+        let b = QReg32::from(&ket("1"));
+        assert!((a.clone() + b.clone()).to_f64().isclose(&ket("+")));
+        assert!((a.clone() - b.clone()).to_f64().isclose(&ket("-")));
+        assert!((a * b).to_f64().isclose(&ket("01")));
+    }
+
+    #[test]
+    fn test_inner_and_bloch_vector() {
+        let plus = QReg32::from(&ket("+"));
+        let zero = QReg32::zeros(1);
+        assert!((plus.fidelity(&zero) - 0.5).abs() < 1e-6);
+        assert!((plus.inner(&plus).re - 1.0).abs() < 1e-6);
+
+        let q = ket("i+").ry(0.3, 0).cnot(0, 1);
+        let q32 = QReg32::from(&ket("i+")).ry(0.3, 0).cnot(0, 1);
+        for qubit in 0..2 {
+            let (x, y, z) = q.bloch_vector(qubit);
+            let (x32, y32, z32) = q32.bloch_vector(qubit);
+            assert!((x - x32).abs() < 1e-5 && (y - y32).abs() < 1e-5 && (z - z32).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_new_normalizes() {
+        let one = Complex32::new(1.0, 0.0);
+        let q = QReg32::new(vec![one, one]);
+        assert!(q.isclose(&QReg32::zeros(1).h(0)));
+    }
+}
@@ -0,0 +1,316 @@
+// Sparse quantum register for rvecsim
+//
+// SparseQReg stores only the populated amplitudes in a HashMap keyed by
+// basis index. Gates touch only populated indices, so circuits that keep
+// the state sparse (e.g. classical reversible ones built from X/CNOT/CCX)
+// run at qubit counts far beyond what a dense 2^n vector allows. Convert
+// to and from `QReg` with `SparseQReg::from(&q)` and `to_dense()`.
+
+use crate::{
+    is_unitary, qterm, QReg, CNOT_GATE, CPHASE_GATE, H_GATE, SKIP_TOL, S_GATE, T_GATE, X_GATE,
+    Y_GATE, Z_GATE,
+};
+use ndarray::{Array1, Array2};
+use num_complex::Complex64;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Quantum register storing only nonzero amplitudes.
+#[derive(Clone)]
+pub struct SparseQReg {
+    pub amps: HashMap<usize, Complex64>,
+    pub n: usize,
+}
+
+impl SparseQReg {
+    /// The n-qubit all-zeros state |00...0>, with a single stored amplitude.
+    pub fn zeros(n: usize) -> Self {
+        assert!(n < usize::BITS as usize, "Too many qubits for a usize index: {n}");
+        SparseQReg {
+            amps: HashMap::from([(0, Complex64::new(1.0, 0.0))]),
+            n,
+        }
+    }
+
+    /// Expand into a dense `QReg`. Allocates 2^n amplitudes.
+    pub fn to_dense(&self) -> QReg {
+        let mut v = Array1::zeros(1usize << self.n);
+        for (&i, &a) in &self.amps {
+            v[i] = a;
+        }
+        QReg { v, n: self.n }
+    }
+
+    /// Number of stored (populated) amplitudes.
+    pub fn support_size(&self) -> usize {
+        self.amps.len()
+    }
+
+    /// Amplitude of basis state `index`, zero if not stored.
+    pub fn amplitude(&self, index: usize) -> Complex64 {
+        self.amps.get(&index).copied().unwrap_or_default()
+    }
+
+    /// Calculate the L2 norm of the state vector.
+    pub fn norm(&self) -> f64 {
+        self.amps.values().map(|a| a.norm_sqr()).sum::<f64>().sqrt()
+    }
+
+    /// Return string representation of the stored terms in basis order.
+    pub fn terms(&self) -> String {
+        let mut idx: Vec<usize> = self.amps.keys().copied().collect();
+        idx.sort_unstable();
+        idx.iter()
+            .map(|&i| qterm(i, self.amps[&i], self.n))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Scatter each stored amplitude through a gate matrix acting on
+    /// `qubits` (qubits[0] is the most significant bit of the matrix index),
+    /// then drop amplitudes that cancelled below `SKIP_TOL`. Zero matrix
+    /// entries are skipped, so permutation gates never grow the support.
+    fn scatter(&mut self, m: &Array2<Complex64>, qubits: &[usize]) {
+        let k = qubits.len();
+        let mask = qubits.iter().fold(0, |acc, &q| acc | (1usize << q));
+        let mut out: HashMap<usize, Complex64> = HashMap::with_capacity(self.amps.len());
+        for (&i, &a) in &self.amps {
+            let col = qubits.iter().fold(0, |acc, &q| (acc << 1) | ((i >> q) & 1));
+            let base = i & !mask;
+            for row in 0..1usize << k {
+                let c = m[[row, col]];
+                if c == Complex64::new(0.0, 0.0) {
+                    continue;
+                }
+                let j = qubits
+                    .iter()
+                    .enumerate()
+                    .fold(base, |acc, (b, &q)| acc | (((row >> (k - 1 - b)) & 1) << q));
+                *out.entry(j).or_default() += c * a;
+            }
+        }
+        out.retain(|_, a| a.norm() >= SKIP_TOL);
+        self.amps = out;
+    }
+
+    /// Apply a single-qubit gate matrix to the target qubit.
+    pub fn apply1q(&mut self, m: &Array2<Complex64>, target: usize) -> &mut Self {
+        assert!(
+            target < self.n,
+            "Invalid target qubit {target}. Must be in [0, {})",
+            self.n
+        );
+        debug_assert!(is_unitary(m, 1e-8), "Single-qubit gate matrix is not unitary");
+        self.scatter(m, &[target]);
+        self
+    }
+
+    /// Apply a two-qubit gate matrix to the control and target qubits,
+    /// with the same matrix ordering as `QReg::apply2q`.
+    pub fn apply2q(
+        &mut self,
+        m: &Array2<Complex64>,
+        control: usize,
+        target: usize,
+    ) -> &mut Self {
+        assert!(
+            control < self.n,
+            "Invalid control qubit {control}. Must be in [0, {})",
+            self.n
+        );
+        assert!(
+            target < self.n,
+            "Invalid target qubit {target}. Must be in [0, {})",
+            self.n
+        );
+        assert!(control != target, "Control and target must be different qubits");
+        debug_assert!(is_unitary(m, 1e-8), "Two-qubit gate matrix is not unitary");
+        self.scatter(m, &[control, target]);
+        self
+    }
+
+    /// Apply a multi-controlled X gate: flip `target` when every qubit in
+    /// `controls` is 1, as in `QReg::mcx`. This only relabels stored
+    /// indices, so the support size never changes.
+    pub fn mcx(&mut self, controls: &[usize], target: usize) -> &mut Self {
+        assert!(
+            target < self.n,
+            "Invalid target qubit {target}. Must be in [0, {})",
+            self.n
+        );
+        for (j, &c) in controls.iter().enumerate() {
+            assert!(
+                c < self.n,
+                "Invalid control qubit {c}. Must be in [0, {})",
+                self.n
+            );
+            assert!(c != target, "Control and target must be different qubits");
+            assert!(!controls[..j].contains(&c), "Duplicate control qubit {c}");
+        }
+
+        let mask = controls.iter().fold(0usize, |m, &c| m | (1 << c));
+        self.amps = self
+            .amps
+            .drain()
+            .map(|(i, a)| if i & mask == mask { (i ^ (1 << target), a) } else { (i, a) })
+            .collect();
+        self
+    }
+
+    // ---- Gate methods (consume self for chaining) ----
+
+    /// Apply Pauli-X (NOT) gate to target qubit.
+    pub fn x(mut self, target: usize) -> Self {
+        self.apply1q(&X_GATE, target);
+        self
+    }
+
+    /// Apply Pauli-Y gate to target qubit.
+    pub fn y(mut self, target: usize) -> Self {
+        self.apply1q(&Y_GATE, target);
+        self
+    }
+
+    /// Apply Pauli-Z gate to target qubit.
+    pub fn z(mut self, target: usize) -> Self {
+        self.apply1q(&Z_GATE, target);
+        self
+    }
+
+    /// Apply Hadamard gate to target qubit. Doubles the support.
+    pub fn h(mut self, target: usize) -> Self {
+        self.apply1q(&H_GATE, target);
+        self
+    }
+
+    /// Apply S (phase) gate to target qubit.
+    pub fn s(mut self, target: usize) -> Self {
+        self.apply1q(&S_GATE, target);
+        self
+    }
+
+    /// Apply T (pi/8) gate to target qubit.
+    pub fn t(mut self, target: usize) -> Self {
+        self.apply1q(&T_GATE, target);
+        self
+    }
+
+    /// Apply controlled-NOT gate.
+    pub fn cnot(mut self, control: usize, target: usize) -> Self {
+        self.apply2q(&CNOT_GATE, control, target);
+        self
+    }
+
+    /// Apply controlled-phase gate.
+    pub fn cphase(mut self, control: usize, target: usize) -> Self {
+        self.apply2q(&CPHASE_GATE, control, target);
+        self
+    }
+}
+
+impl From<&QReg> for SparseQReg {
+    fn from(q: &QReg) -> Self {
+        let amps = q
+            .v
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.norm() >= SKIP_TOL)
+            .map(|(i, &a)| (i, a))
+            .collect();
+        SparseQReg { amps, n: q.n }
+    }
+}
+
+impl fmt::Display for SparseQReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.terms())
+    }
+}
+
+impl fmt::Debug for SparseQReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SparseQReg")
+            .field("n", &self.n)
+            .field("terms", &self.terms())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ket, zeros};
+
+    /// X on every even qubit, then a CNOT ladder copying each into the next.
+    fn classical_circuit(n: usize) -> SparseQReg {
+        let mut q = SparseQReg::zeros(n);
+        for i in (0..n).step_by(2) {
+            q = q.x(i);
+        }
+        for i in 0..n - 1 {
+            q = q.cnot(i, i + 1);
+        }
+        q
+    }
+
+    #[test]
+    fn test_classical_circuit_30_qubits() {
+        let q = classical_circuit(30);
+        assert_eq!(q.support_size(), 1);
+        // Each bit is the parity of the X pattern up to it: 1,1,0,0,1,1,...
+        let expected = (0..30).filter(|i| i % 4 < 2).fold(0, |acc, i| acc | (1 << i));
+        assert!((q.amplitude(expected) - 1.0).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_classical_circuit_matches_dense() {
+        let n = 8;
+        let mut dense = zeros(n);
+        for i in (0..n).step_by(2) {
+            dense = dense.x(i);
+        }
+        for i in 0..n - 1 {
+            dense = dense.cnot(i, i + 1);
+        }
+        assert!(classical_circuit(n).to_dense().isclose(&dense));
+    }
+
+    #[test]
+    fn test_mcx_matches_dense() {
+        let mut sq = SparseQReg::from(&ket("0111"));
+        sq.mcx(&[0, 1, 2], 3);
+        assert_eq!(sq.support_size(), 1);
+        assert!(sq.to_dense().isclose(&ket("1111")));
+
+        // Toffoli on a superposition only flips the branch with both controls set
+        let mut dense = ket("0++");
+        dense.mcx(&[0, 1], 2);
+        let mut sq = SparseQReg::from(&ket("0++"));
+        sq.mcx(&[0, 1], 2);
+        assert!(sq.to_dense().isclose(&dense));
+    }
+
+    #[test]
+    fn test_gates_track_dense() {
+        let q = ket("0110").h(0).s(1).y(2).cnot(0, 3).t(3).z(1).cphase(2, 0).h(0);
+        let sq = SparseQReg::from(&ket("0110")).h(0).s(1).y(2).cnot(0, 3).t(3).z(1).cphase(2, 0).h(0);
+        assert!(sq.to_dense().isclose(&q));
+        assert!((sq.norm() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_interference_prunes_support() {
+        let q = SparseQReg::zeros(3).h(1);
+        assert_eq!(q.support_size(), 2);
+        let q = q.h(1);
+        assert_eq!(q.support_size(), 1);
+        assert_eq!(q.to_string(), zeros(3).h(1).h(1).to_string());
+    }
+
+    #[test]
+    fn test_bell_display() {
+        let bell = SparseQReg::zeros(2).h(0).cnot(0, 1);
+        assert_eq!(bell.to_string(), ket("00").h(0).cnot(0, 1).to_string());
+        assert!(SparseQReg::from(&bell.to_dense()).to_dense().isclose(&bell.to_dense()));
+    }
+}
@@ -6,7 +6,13 @@ Run with: python test_python_bindings.py
 (Make sure to activate the virtual environment and run `maturin develop --features pyo3` first)
 """
 
-from rvecsim import ket
+import math
+import pickle
+
+import numpy as np
+
+import rvecsim
+from rvecsim import QReg, ket, basis, uniform, w_state, random, superpose
 
 def test_basic_operations():
     """Test basic quantum operations."""
@@ -22,10 +28,65 @@ def test_basic_operations():
     q_plus = ket('0').H(0)
     print(f"H|0> = |+>: {q_plus}")
 
+    # Run-length repeat syntax
+    print(f"ket('01*2'): {ket('01*2')}")
+    assert ket('0*3').isclose(ket('000'))
+    try:
+        ket('0*abc')
+        assert False, "expected ValueError"
+    except ValueError as e:
+        print(f"ket('0*abc') raises: {e}")
+    try:
+        ket('0*64')
+        assert False, "expected ValueError"
+    except ValueError as e:
+        print(f"ket('0*64') raises: {e}")
+
+    # Computational basis states by index
+    print(f"basis(3, 5): {basis(3, 5)}")
+    assert str(basis(3, 5)) == "1.0|101>"
+
+    # Uniform superposition of every basis state
+    print(f"uniform(2): {uniform(2)}")
+    assert uniform(3).isclose(ket('+++'))
+
+    # W state: equal weight on every single-excitation basis state
+    w = w_state(3)
+    print(f"w_state(3): {w}")
+    assert w.isclose(superpose([(1, basis(3, 1)), (1, basis(3, 2)), (1, basis(3, 4))]))
+
+    # Haar-random states are normalized and reproducible with a seed
+    r = random(3, seed=1)
+    print(f"random(3, seed=1) norm: {r.norm}")
+    assert abs(r.norm - 1.0) < 1e-10
+    assert r.isclose(random(3, seed=1))
+    try:
+        random(64)
+        assert False, "expected ValueError"
+    except ValueError as e:
+        print(f"random(64) raises: {e}")
+    assert not r.isclose(random(3, seed=2))
+
     # Pauli gates
     print(f"X|0> = |1>: {ket('0').X(0)}")
     print(f"Z|1> = -|1>: {ket('1').Z(0)}")
 
+    # Rotations: RX(pi) takes |0> to -i|1>
+    print(f"ket('0').RX(pi): {ket('0').RX(math.pi, 0)}")
+    assert abs(abs(ket('0').RX(math.pi, 0).amplitude('1')) - 1.0) < 1e-10
+    assert ket('0').RY(math.pi / 2, 0).isclose(ket('+'))
+    assert ket('+').RZ(math.pi, 0).isclose_up_to_phase(ket('-'))
+    assert ket('+').P(math.pi / 2, 0).isclose(ket('+').S(0))
+    try:
+        ket('0').RX(1.0, 1)
+        assert False, "expected ValueError"
+    except ValueError as e:
+        print(f"RX on qubit 1 of 1 raises: {e}")
+
+    # Hadamard on every qubit gives the uniform superposition
+    print(f"ket('000').Hall(): {ket('000').Hall()}")
+    assert ket('000').Hall().isclose(ket('+++'))
+
     print()
 
 def test_entanglement():
@@ -35,10 +96,83 @@ def test_entanglement():
     # Bell state
     bell = ket('00').H(0).CNOT(0, 1)
     print(f"Bell state (|00> + |11>)/√2: {bell}")
+    assert bell.isclose(rvecsim.bell())
 
     # GHZ state
     ghz = ket('000').H(0).CNOT(0, 1).CNOT(1, 2)
     print(f"GHZ state (|000> + |111>)/√2: {ghz}")
+    assert ghz.isclose(rvecsim.ghz(3))
+
+    # LaTeX rendering for notebooks
+    print(f"Bell _repr_latex_(): {bell._repr_latex_()}")
+    assert bell._repr_latex_() == r"$\frac{1}{\sqrt{2}}(|00\rangle + |11\rangle)$"
+
+    # Building directly from amplitudes normalizes them
+    direct = QReg.from_amplitudes([1, 0, 0, 1])
+    print(f"QReg.from_amplitudes([1, 0, 0, 1]): {direct}")
+    assert direct.isclose(bell)
+    assert QReg.from_amplitudes([1j, 1]).isclose([1j / 2 ** 0.5, 1 / 2 ** 0.5])
+    for bad in ([1, 0, 0], [0, 0]):
+        try:
+            QReg.from_amplitudes(bad)
+            assert False, "expected ValueError"
+        except ValueError as e:
+            print(f"QReg.from_amplitudes({bad}) raises: {e}")
+
+    # Pickle round trip, e.g. for multiprocessing
+    restored = pickle.loads(pickle.dumps(bell))
+    print(f"pickle round trip of Bell state: {restored}")
+    assert restored.isclose(bell)
+    for state in [(64, [1]), (70, [1, 0]), (1, [0, 0])]:
+        try:
+            QReg().__setstate__(state)
+            assert False, "expected ValueError"
+        except ValueError as e:
+            print(f"__setstate__({state}) raises: {e}")
+
+    # Qubit order reversal
+    print(f"ket('100').reverse_qubits(): {ket('100').reverse_qubits()}")
+    assert ket('100').reverse_qubits().isclose(ket('001'))
+
+    # Complex conjugation maps |i> to |-i>
+    print(f"ket('i').conjugate(): {ket('i').conjugate()}")
+    assert ket('i').conjugate().isclose(ket('j'))
+
+    # Most probable outcome
+    idx, p = bell.argmax()
+    print(f"Bell argmax(): ({idx}, {p:.3f})")
+    assert idx in (0, 3) and abs(p - 0.5) < 1e-10
+
+    # Amplitude lookup by bitstring
+    print(f"Bell amplitude('11'): {bell.amplitude('11')}")
+    assert abs(bell.amplitude('11') - 2 ** -0.5) < 1e-10
+    assert abs(bell.amplitude('01')) < 1e-10
+
+    # Structured (bitstring, amplitude) dump
+    terms = bell.to_terms_vec()
+    print(f"Bell to_terms_vec(): {terms}")
+    assert [bits for bits, _ in terms] == ['00', '11']
+    assert all(abs(amp - 2 ** -0.5) < 1e-10 for _, amp in terms)
+
+    # Number of populated basis states
+    print(f"Bell support_size(): {bell.support_size()}")
+    assert bell.support_size() == 2
+    assert ket('+++').support_size() == 8
+
+    # Joint probability of a full outcome
+    print(f"Bell prob('11'): {bell.prob('11'):.3f}")
+    assert abs(bell.prob('11') - 0.5) < 1e-10
+    assert bell.prob('01') < 1e-10
+
+    # Multi-controlled X flips the target only when all controls are 1
+    print(f"MCX([0, 1, 2], 3) on |0111>: {ket('0111').MCX([0, 1, 2], 3)}")
+    assert ket('0111').MCX([0, 1, 2], 3).isclose(ket('1111'))
+    assert ket('0101').MCX([0, 1, 2], 3).isclose(ket('0101'))
+
+    # Fredkin gate swaps qubits 0 and 1 only when qubit 2 is set
+    print(f"CSWAP(2, 0, 1) on |101>: {ket('101').CSWAP(2, 0, 1)}")
+    assert ket('101').CSWAP(2, 0, 1).isclose(ket('110'))
+    assert ket('001').CSWAP(2, 0, 1).isclose(ket('001'))
 
     print()
 
@@ -54,6 +188,17 @@ def test_operators():
     q_minus = ket('0') - ket('1')
     print(f"|0> - |1> = |->: {q_minus}")
 
+    # Weighted superposition (|0> + 2|1>)/sqrt(5)
+    q = superpose([(1, ket('0')), (2, ket('1'))])
+    print(f"superpose([(1, |0>), (2, |1>)]): {q}")
+    assert q.isclose([1 / 5 ** 0.5, 2 / 5 ** 0.5])
+    for bad in ([], [(1, ket('0')), (-1, ket('0'))], [(1, ket('0')), (1, ket('00'))]):
+        try:
+            superpose(bad)
+            assert False, "expected ValueError"
+        except ValueError as e:
+            print(f"superpose({len(bad)} terms) raises: {e}")
+
     # Tensor product
     q_01 = ket('0') * ket('1')
     print(f"|0> ⊗ |1> = |01>: {q_01}")
@@ -75,6 +220,40 @@ def test_measurement():
     print(f"Measure |+> 10 times: {result}")
     print(f"  (Should be mix of 0s and 1s)")
 
+    # Seeded measurement is reproducible
+    first = [ket('+').M(0, seed=s)[0] for s in range(10)]
+    second = [ket('+').M(0, seed=s)[0] for s in range(10)]
+    print(f"M(0, seed=s) on |+> for s in 0..10: {first}")
+    assert first == second
+
+    # Measuring |+> in the X basis always gives the +1 outcome
+    result = ket('+').measure_basis(0, 'x', 10)
+    print(f"measure_basis(0, 'x', 10) on |+>: {result}")
+    assert result == [0] * 10
+
+    # Measure all qubits at once: a Bell state only ever yields |00> or |11>
+    outcomes = [ket('00').H(0).CNOT(0, 1).Mall() for _ in range(20)]
+    print(f"Mall() on Bell state 20 times: {outcomes}")
+    assert all(o in (0, 3) for o in outcomes)
+    seeded = [ket('++').Mall(seed=s) for s in range(10)]
+    assert seeded == [ket('++').Mall(seed=s) for s in range(10)]
+
+    # Sampling does not collapse the state
+    q = ket('+')
+    shots = q.sample(0, 1000)
+    print(f"sample(0, 1000) on |+>: {shots.count(0)} zeros, {shots.count(1)} ones")
+    assert 400 <= shots.count(1) <= 600
+    assert q.isclose(ket('+'))
+    assert q.sample(0, 100, seed=5) == q.sample(0, 100, seed=5)
+
+    # Counts histogram over the full register
+    counts = ket('00').H(0).CNOT(0, 1).sample_counts(1000)
+    print(f"sample_counts(1000) on Bell state: {counts}")
+    assert set(counts) <= {'00', '11'}
+    assert sum(counts.values()) == 1000
+    bell = ket('00').H(0).CNOT(0, 1)
+    assert bell.sample_counts(100, seed=5) == bell.sample_counts(100, seed=5)
+
     print()
 
 def test_comparison():
@@ -90,6 +269,26 @@ def test_comparison():
     is_close = ket('+').isclose(ket('0') + ket('1'))
     print(f"ket('+').isclose(ket('0') + ket('1')): {is_close}")
 
+    # Comparison up to a global phase
+    print(f"(-|1>).isclose_up_to_phase(|1>): {ket('1').Z(0).isclose_up_to_phase(ket('1'))}")
+    assert ket('1').Z(0).isclose_up_to_phase(ket('1'))
+    assert not ket('1').Z(0).isclose(ket('1'))
+
+    # Fidelity and trace distance
+    print(f"trace_distance(|0>, |1>): {ket('0').trace_distance(ket('1'))}")
+    assert ket('+').trace_distance(ket('+')) < 1e-6
+    assert abs(ket('0').trace_distance(ket('1')) - 1.0) < 1e-10
+    assert abs(ket('+').fidelity(ket('0')) - 0.5) < 1e-10
+
+    # == is approximate equality; len() is the number of amplitudes
+    bell1 = ket('00').H(0).CNOT(0, 1)
+    bell2 = superpose([(1, ket('00')), (1, ket('11'))])
+    print(f"Bell states equal: {bell1 == bell2}, len(ket('00')): {len(ket('00'))}")
+    assert bell1 == bell2
+    assert bell1 != ket('00')
+    assert bell1 != "not a QReg"
+    assert len(ket('00')) == 4
+
     print()
 
 def test_properties():
@@ -101,6 +300,27 @@ def test_properties():
     print(f"Norm: {q.norm}")
     print(f"Amplitudes: {q.amplitudes}")
 
+    arr = q.to_numpy()
+    print(f"to_numpy(): {arr!r}")
+    assert arr.dtype == np.complex128
+    assert arr.shape == (4,)
+    assert np.allclose(arr, 0.5)
+
+    rho = ket('+').density_matrix()
+    print(f"ket('+').density_matrix(): {rho!r}")
+    assert rho.shape == (2, 2)
+    assert np.allclose(rho, 0.5)
+
+    # Product-state detection across a bipartition
+    bell = ket('00').H(0).CNOT(0, 1)
+    print(f"Bell is_product([0]): {bell.is_product([0])}")
+    assert not bell.is_product([0])
+    assert ket('01').is_product([0])
+
+    x, y, z = ket('+').bloch_vector(0)
+    print(f"Bloch vector of |+>: ({x:.3f}, {y:.3f}, {z:.3f})")
+    assert abs(x - 1.0) < 1e-10 and abs(y) < 1e-10 and abs(z) < 1e-10
+
     print()
 
 def main():